@@ -0,0 +1,109 @@
+use crate::provider::{ProviderError, TranslationProvider};
+use common::ProviderUsage;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Fetches the upstream account usage, caches it for `ttl` and logs a warning
+/// whenever usage crosses one of the configured thresholds.
+pub struct AccountUsageMonitor {
+    provider: Arc<dyn TranslationProvider>,
+    ttl: Duration,
+    /// Fractions of the limit (0.0-1.0), ascending
+    thresholds: Vec<f64>,
+    // Held across the upstream call so concurrent requests share one fetch
+    cached: tokio::sync::Mutex<Option<(Instant, ProviderUsage)>>,
+    /// Last fetched usage, readable while a fetch is in flight
+    latest: Mutex<Option<ProviderUsage>>,
+    /// Highest threshold already warned about in the current billing period
+    warned: Mutex<Option<f64>>,
+}
+
+impl AccountUsageMonitor {
+    pub fn new(
+        provider: Arc<dyn TranslationProvider>,
+        ttl: Duration,
+        mut thresholds: Vec<f64>,
+    ) -> Self {
+        thresholds.retain(|t| (0.0..=1.0).contains(t));
+        thresholds.sort_by(|a, b| a.total_cmp(b));
+        Self {
+            provider,
+            ttl,
+            thresholds,
+            cached: tokio::sync::Mutex::new(None),
+            latest: Mutex::new(None),
+            warned: Mutex::new(None),
+        }
+    }
+
+    pub async fn get(&self) -> Result<ProviderUsage, ProviderError> {
+        let mut cached = self.cached.lock().await;
+        if let Some((fetched_at, usage)) = &*cached {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(usage.clone());
+            }
+        }
+
+        let account = self.provider.account_usage().await?;
+        let usage = ProviderUsage {
+            provider: self.provider.name().to_string(),
+            character_count: account.character_count,
+            character_limit: account.character_limit,
+        };
+        self.check_thresholds(&usage);
+        *cached = Some((Instant::now(), usage.clone()));
+        *self.latest.lock().unwrap() = Some(usage.clone());
+        Ok(usage)
+    }
+
+    /// The usage from the last successful fetch, however old, without
+    /// calling the upstream
+    pub fn latest(&self) -> Option<ProviderUsage> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    fn check_thresholds(&self, usage: &ProviderUsage) {
+        let fraction = usage.fraction_used();
+        let crossed = self.thresholds.iter().copied().rfind(|t| fraction >= *t);
+
+        let mut warned = self.warned.lock().unwrap();
+        match crossed {
+            Some(threshold) if warned.is_none_or(|w| threshold > w) => {
+                warn!(
+                    "{} account usage at {:.1}% ({} of {} characters), above the {:.0}% threshold",
+                    usage.provider,
+                    fraction * 100.0,
+                    usage.character_count,
+                    usage.character_limit,
+                    threshold * 100.0
+                );
+                *warned = Some(threshold);
+            }
+            // Usage went back down: a new billing period started
+            None if warned.is_some() => {
+                info!(
+                    "{} account usage back below warning thresholds",
+                    usage.provider
+                );
+                *warned = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Refreshes the usage every `interval` so threshold warnings are logged
+    /// even when nobody asks for them
+    pub async fn monitor_periodically(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match self.get().await {
+                Ok(_) => {}
+                // The provider has no usage API, nothing to monitor
+                Err(ProviderError::Unsupported(_)) => return,
+                Err(e) => warn!("Failed to fetch account usage: {}", e),
+            }
+        }
+    }
+}
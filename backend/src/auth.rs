@@ -0,0 +1,168 @@
+use crate::error::AppError;
+use crate::usage::Quota;
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use common::ApiError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// One entry of the tokens file
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserConfig {
+    pub name: String,
+    pub token: String,
+    #[serde(default)]
+    pub admin: bool,
+    /// Overrides the default daily character limit for this user
+    pub daily_char_limit: Option<u64>,
+    /// Overrides the default monthly character limit for this user
+    pub monthly_char_limit: Option<u64>,
+}
+
+/// Format of `PROXY_TOKENS_FILE`:
+/// `{"users": [{"name": "alice", "token": "...", "admin": false, "daily_char_limit": 50000}]}`
+#[derive(Debug, Deserialize)]
+struct TokensFile {
+    users: Vec<UserConfig>,
+}
+
+/// The caller of a request, inserted into request extensions by `require_auth`
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub name: String,
+    pub admin: bool,
+    pub quota: Quota,
+}
+
+impl AuthUser {
+    fn anonymous(quota: Quota) -> Self {
+        Self {
+            name: "anonymous".to_string(),
+            admin: false,
+            quota,
+        }
+    }
+
+    pub fn require_admin(&self) -> Result<(), AppError> {
+        if self.admin {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden("Admin access required".to_string()).into())
+        }
+    }
+}
+
+pub struct Authenticator {
+    /// Token -> user
+    users: HashMap<String, AuthUser>,
+    /// Anonymous callers are only let through with `ALLOW_ANONYMOUS`
+    required: bool,
+    /// Applies to users without their own limits
+    default_quota: Quota,
+}
+
+impl Authenticator {
+    /// Loads proxy tokens from `tokens_file`. `admin_token`, if set, is accepted
+    /// as an additional admin credential. Fails without any token unless
+    /// `allow_anonymous` opts out of authentication.
+    pub fn load(
+        tokens_file: Option<&Path>,
+        admin_token: Option<String>,
+        default_quota: Quota,
+        allow_anonymous: bool,
+    ) -> anyhow::Result<Self> {
+        let mut users = HashMap::new();
+
+        if let Some(path) = tokens_file {
+            let data = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+            let file: TokensFile = serde_json::from_str(&data)
+                .map_err(|e| anyhow::anyhow!("Invalid tokens file {}: {}", path.display(), e))?;
+
+            for user in file.users {
+                if user.token.trim().is_empty() {
+                    anyhow::bail!("User '{}' has an empty token", user.name);
+                }
+                let auth_user = AuthUser {
+                    name: user.name,
+                    admin: user.admin,
+                    quota: Quota {
+                        daily: user.daily_char_limit.or(default_quota.daily),
+                        monthly: user.monthly_char_limit.or(default_quota.monthly),
+                    },
+                };
+                if users.insert(user.token, auth_user).is_some() {
+                    anyhow::bail!("Duplicate token in {}", path.display());
+                }
+            }
+        }
+
+        if let Some(token) = admin_token {
+            users.entry(token).or_insert(AuthUser {
+                name: "admin".to_string(),
+                admin: true,
+                quota: default_quota,
+            });
+        }
+
+        if users.is_empty() && !allow_anonymous {
+            anyhow::bail!(
+                "No API tokens configured: set PROXY_TOKENS_FILE or ADMIN_TOKEN, \
+                 or ALLOW_ANONYMOUS=1 to run the proxy without authentication"
+            );
+        }
+
+        Ok(Self {
+            users,
+            required: !allow_anonymous,
+            default_quota,
+        })
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    pub fn user_count(&self) -> usize {
+        self.users.len()
+    }
+
+    fn authenticate(&self, token: Option<&str>) -> Result<AuthUser, ApiError> {
+        match token {
+            Some(token) => self
+                .users
+                .get(token)
+                .cloned()
+                .ok_or_else(|| ApiError::Unauthorized("Invalid API token".to_string())),
+            None if self.required => Err(ApiError::Unauthorized(
+                "Missing API token; send Authorization: Bearer <token>".to_string(),
+            )),
+            None => Ok(AuthUser::anonymous(self.default_quota)),
+        }
+    }
+}
+
+/// Rejects requests without a valid bearer token and makes the caller
+/// available to handlers as `Extension<AuthUser>`.
+pub async fn require_auth(
+    State(auth): State<Arc<Authenticator>>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    let user = auth.authenticate(token)?;
+    req.extensions_mut().insert(user);
+    Ok(next.run(req).await)
+}
@@ -0,0 +1,66 @@
+use crate::provider::{ProviderError, TranslateOptions, Translation, TranslationProvider};
+use crate::usage::{billed_chars, PartialFailure};
+
+// DeepL accepts at most 50 texts and 128 KiB of request body per call.
+// Keep some headroom for the JSON envelope and escaping.
+const MAX_TEXTS_PER_REQUEST: usize = 50;
+const MAX_TEXT_BYTES_PER_REQUEST: usize = 120 * 1024;
+
+/// Splits `texts` into chunks that fit the upstream per-request limits.
+/// A single text larger than the byte limit still gets its own chunk and is
+/// left for the upstream to reject.
+fn split_into_chunks(texts: Vec<String>) -> Vec<Vec<String>> {
+    let mut chunks = Vec::new();
+    let mut current = Vec::new();
+    let mut current_bytes = 0;
+
+    for text in texts {
+        let full = current.len() >= MAX_TEXTS_PER_REQUEST
+            || current_bytes + text.len() > MAX_TEXT_BYTES_PER_REQUEST;
+        if full && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+            current_bytes = 0;
+        }
+        current_bytes += text.len();
+        current.push(text);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Translates any number of texts, issuing as many upstream calls as the
+/// per-request limits require. Results keep the order of `texts`. On failure
+/// the error tells how many characters the chunks answered so far were billed.
+pub async fn translate_batched(
+    provider: &dyn TranslationProvider,
+    texts: Vec<String>,
+    options: &TranslateOptions,
+) -> Result<Vec<Translation>, PartialFailure> {
+    let mut translations = Vec::with_capacity(texts.len());
+    let mut billed = 0;
+
+    for chunk in split_into_chunks(texts) {
+        let expected = chunk.len();
+        let chars = billed_chars(&chunk);
+        let chunk_translations = provider
+            .translate(chunk, options)
+            .await
+            .map_err(|error| PartialFailure { error, billed })?;
+        // The upstream answered, so it billed the chunk even if results are missing
+        billed += chars;
+        if chunk_translations.len() != expected {
+            return Err(PartialFailure {
+                error: ProviderError::IncompleteResponse {
+                    expected,
+                    received: chunk_translations.len(),
+                },
+                billed,
+            });
+        }
+        translations.extend(chunk_translations);
+    }
+
+    Ok(translations)
+}
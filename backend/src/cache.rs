@@ -0,0 +1,210 @@
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheOperation {
+    Translate,
+    Improve,
+}
+
+/// Identifies a cached upstream result. `options` is the serialized provider
+/// options (languages, style, ...), so any option change is a different entry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CacheKey {
+    operation: CacheOperation,
+    options: String,
+    text: String,
+}
+
+impl CacheKey {
+    pub fn new<O: Serialize>(operation: CacheOperation, text: &str, options: &O) -> Self {
+        Self {
+            operation,
+            options: serde_json::to_string(options).unwrap_or_default(),
+            text: normalize_text(text),
+        }
+    }
+}
+
+// Surrounding whitespace and line ending style do not change the translation
+fn normalize_text(text: &str) -> String {
+    text.trim().replace("\r\n", "\n")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResult {
+    pub result: String,
+    pub detected_source_language: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    value: CachedResult,
+    inserted_at: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub max_entries: NonZeroUsize,
+    /// Texts longer than this are never cached
+    pub max_text_bytes: usize,
+    pub ttl: Duration,
+    /// Where to persist entries across restarts, if anywhere
+    pub persist_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub max_entries: usize,
+    pub ttl_secs: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// In-memory LRU cache of upstream results, optionally persisted to disk
+pub struct TranslationCache {
+    config: CacheConfig,
+    entries: Mutex<LruCache<CacheKey, CacheEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl TranslationCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(config.max_entries)),
+            config,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn is_fresh(&self, entry: &CacheEntry) -> bool {
+        entry
+            .inserted_at
+            .elapsed()
+            .map(|age| age < self.config.ttl)
+            .unwrap_or(false)
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<CachedResult> {
+        let mut entries = self.entries.lock().unwrap();
+        let value = match entries.get(key) {
+            Some(entry) if self.is_fresh(entry) => Some(entry.value.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        };
+
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    pub fn insert(&self, key: CacheKey, value: CachedResult) {
+        if key.text.len() > self.config.max_text_bytes {
+            return;
+        }
+        let entry = CacheEntry {
+            value,
+            inserted_at: SystemTime::now(),
+        };
+        self.entries.lock().unwrap().put(key, entry);
+    }
+
+    /// Drops every entry and returns how many there were
+    pub fn purge(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let purged = entries.len();
+        entries.clear();
+        purged
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.entries.lock().unwrap().len(),
+            max_entries: self.config.max_entries.get(),
+            ttl_secs: self.config.ttl.as_secs(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Loads persisted entries, skipping expired ones. A missing file is not an error.
+    pub async fn load(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.config.persist_path else {
+            return Ok(());
+        };
+        let data = match tokio::fs::read(path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let persisted: Vec<(CacheKey, CacheEntry)> = serde_json::from_slice(&data)?;
+
+        let mut entries = self.entries.lock().unwrap();
+        // Persisted least recently used first, so the LRU order is restored
+        for (key, entry) in persisted {
+            if self.is_fresh(&entry) {
+                entries.put(key, entry);
+            }
+        }
+        info!(
+            "Loaded {} cache entries from {}",
+            entries.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    /// Writes all fresh entries to the persistence file, if one is configured
+    pub async fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.config.persist_path else {
+            return Ok(());
+        };
+        let data = {
+            let entries = self.entries.lock().unwrap();
+            let persisted: Vec<(&CacheKey, &CacheEntry)> = entries
+                .iter()
+                .rev()
+                .filter(|(_, entry)| self.is_fresh(entry))
+                .collect();
+            serde_json::to_vec(&persisted)?
+        };
+
+        // Write to a temporary file first so a crash never leaves a truncated cache
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, data).await?;
+        tokio::fs::rename(&tmp_path, path).await?;
+        Ok(())
+    }
+
+    /// Persists the cache every `interval` until the process exits
+    pub async fn persist_periodically(&self, interval: Duration) {
+        if self.config.persist_path.is_none() {
+            return;
+        }
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.save().await {
+                warn!("Failed to persist translation cache: {}", e);
+            }
+        }
+    }
+}
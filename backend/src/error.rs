@@ -0,0 +1,110 @@
+use crate::provider::ProviderError;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use common::{ApiError, ApiResponse};
+use serde::Deserialize;
+use tracing::warn;
+
+/// Error returned from handlers. Rendered as an `ApiResponse` carrying the
+/// `ApiError`, with an HTTP status matching its kind.
+#[derive(Debug)]
+pub struct AppError(pub ApiError);
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self.0 {
+            ApiError::BadInput(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::RateLimited(_) | ApiError::UserQuotaExceeded(_) => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            ApiError::QuotaExceeded(_) | ApiError::UpstreamUnavailable(_) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            // The upstream rejecting our key is a proxy misconfiguration, not the caller's fault
+            ApiError::AuthFailure(_)
+            | ApiError::ParseFailure(_)
+            | ApiError::OutputCheckFailed(_) => StatusCode::BAD_GATEWAY,
+            ApiError::Unsupported(_) => StatusCode::NOT_IMPLEMENTED,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let body = ApiResponse {
+            result: String::new(),
+            success: false,
+            error: Some(self.0),
+            detected_source_language: None,
+            diff: None,
+            applied_terms: Vec::new(),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+impl From<ApiError> for AppError {
+    fn from(error: ApiError) -> Self {
+        AppError(error)
+    }
+}
+
+// Error bodies of DeepL ({"message": ...}) and LibreTranslate ({"error": ...})
+#[derive(Deserialize)]
+struct UpstreamErrorBody {
+    message: Option<String>,
+    error: Option<String>,
+}
+
+fn upstream_message(body: &str) -> Option<String> {
+    serde_json::from_str::<UpstreamErrorBody>(body)
+        .ok()
+        .and_then(|b| b.message.or(b.error))
+        .filter(|m| !m.is_empty())
+}
+
+impl From<ProviderError> for AppError {
+    fn from(e: ProviderError) -> Self {
+        warn!("Upstream request failed: {}", e);
+        let error = match e {
+            ProviderError::Network(_) => {
+                ApiError::UpstreamUnavailable("Could not reach the translation service".to_string())
+            }
+            ProviderError::Status { status, body, .. } => {
+                let detail = upstream_message(&body).unwrap_or_else(|| status.to_string());
+                match status.as_u16() {
+                    400 | 413 | 414 => ApiError::BadInput(detail),
+                    401 | 403 => ApiError::AuthFailure(detail),
+                    404 => ApiError::NotFound(detail),
+                    429 => ApiError::RateLimited(detail),
+                    // DeepL signals an exhausted character quota with 456
+                    456 => ApiError::QuotaExceeded(detail),
+                    _ => ApiError::UpstreamUnavailable(detail),
+                }
+            }
+            ProviderError::Parse(_) => {
+                ApiError::ParseFailure("Failed to parse upstream response".to_string())
+            }
+            ProviderError::IncompleteResponse { expected, received } => {
+                ApiError::ParseFailure(format!(
+                    "Expected {} results from upstream, received {}",
+                    expected, received
+                ))
+            }
+            ProviderError::CircuitOpen { retry_in } => ApiError::UpstreamUnavailable(format!(
+                "The translation service is temporarily unavailable, retry in {}s",
+                retry_in.as_secs().max(1)
+            )),
+            ProviderError::Unsupported(what) => {
+                ApiError::Unsupported(format!("The configured provider does not support {}", what))
+            }
+        };
+        AppError(error)
+    }
+}
@@ -0,0 +1,582 @@
+use crate::auth::AuthUser;
+use crate::batch;
+use crate::cache::{CacheKey, CacheOperation, CachedResult};
+use crate::error::AppError;
+use crate::formats::fluent;
+use crate::formats::po::{self, PoTranslation};
+use crate::formats::resources::{self, KeyPath};
+use crate::formats::subtitles;
+use crate::formats::xliff;
+use crate::languages::LanguageCatalog;
+use crate::markup;
+use crate::placeholders::ProtectedText;
+use crate::provider::TranslateOptions;
+use crate::terminology::MaskedText;
+use crate::usage::billed_chars;
+use crate::AppState;
+use axum::extract::State;
+use axum::{Extension, Json};
+use common::{
+    ApiError, AppliedTerm, FluentTranslateRequest, FluentTranslateResponse, PoTranslateRequest,
+    PoTranslateResponse, ResourceTranslateRequest, ResourceTranslateResponse,
+    SubtitleTranslateRequest, SubtitleTranslateResponse, TagHandling, TagOptions,
+    TranslatedResource, XliffTranslateRequest, XliffTranslateResponse,
+};
+use futures::stream::{self, StreamExt};
+use std::collections::HashMap;
+use tracing::warn;
+
+// Upstream calls a PO catalog may have running at once, one per msgctxt
+const PO_CONTEXTS_IN_FLIGHT: usize = 4;
+
+/// Result of [`translate_segments`]
+pub struct TranslatedSegments {
+    /// Per segment, in order: the translation, or why it failed its checks
+    pub texts: Vec<Result<String, ApiError>>,
+    pub applied_terms: Vec<AppliedTerm>,
+    pub detected_source_language: Option<String>,
+    /// Whether every segment came from the cache
+    pub cached: bool,
+}
+
+/// Translates the segments of one document: local terminology is masked, cached
+/// segments are reused and the rest go upstream in batched calls. A segment
+/// whose placeholders did not survive fails on its own and is not cached.
+pub async fn translate_segments(
+    state: &AppState,
+    user: &AuthUser,
+    segments: &[ProtectedText],
+    options: &TranslateOptions,
+) -> Result<TranslatedSegments, AppError> {
+    let masked: Vec<MaskedText> = segments
+        .iter()
+        .map(|segment| state.terminology.mask(&segment.text, options))
+        .collect();
+    let keys: Vec<CacheKey> = masked
+        .iter()
+        .map(|m| CacheKey::new(CacheOperation::Translate, &m.text, options))
+        .collect();
+    let mut values: Vec<Option<Result<CachedResult, ApiError>>> = keys
+        .iter()
+        .map(|key| state.cache.get(key).map(Ok))
+        .collect();
+
+    let missing: Vec<usize> = (0..values.len()).filter(|&i| values[i].is_none()).collect();
+    let cached = missing.is_empty();
+    if !missing.is_empty() {
+        let texts: Vec<String> = missing.iter().map(|&i| masked[i].text.clone()).collect();
+        let billed = billed_chars(&texts);
+        let translations = state
+            .usage
+            .metered(
+                user,
+                billed,
+                batch::translate_batched(state.provider.as_ref(), texts, options),
+            )
+            .await?;
+        for (i, translation) in missing.into_iter().zip(translations) {
+            let value = CachedResult {
+                result: translation.text,
+                detected_source_language: translation.detected_source_language,
+            };
+            values[i] = Some(segments[i].check(&value.result).map(|()| {
+                state.cache.insert(keys[i].clone(), value.clone());
+                value
+            }));
+        }
+    }
+
+    let mut translated = TranslatedSegments {
+        texts: Vec::with_capacity(segments.len()),
+        applied_terms: Vec::new(),
+        detected_source_language: None,
+        cached,
+    };
+    for ((segment, masked), value) in segments
+        .iter()
+        .zip(&masked)
+        .zip(values.into_iter().flatten())
+    {
+        let value = match value {
+            Ok(value) => value,
+            Err(error) => {
+                translated.texts.push(Err(error));
+                continue;
+            }
+        };
+        let (result, terms) = masked.restore(&value.result);
+        translated.texts.push(segment.restore(&result));
+        for term in terms {
+            if !translated.applied_terms.contains(&term) {
+                translated.applied_terms.push(term);
+            }
+        }
+        if translated.detected_source_language.is_none() {
+            translated.detected_source_language = value.detected_source_language;
+        }
+    }
+    Ok(translated)
+}
+
+fn translate_options(
+    source_lang: Option<String>,
+    target_lang: Option<String>,
+    formality: Option<String>,
+    glossary_id: Option<String>,
+) -> TranslateOptions {
+    TranslateOptions {
+        source_lang: source_lang.filter(|s| !s.is_empty()),
+        target_lang: target_lang.unwrap_or_else(|| "EN".to_string()),
+        formality: formality.filter(|f| !f.is_empty()),
+        glossary_id: glossary_id.filter(|g| !g.is_empty()),
+        tags: TagOptions::default(),
+        context: None,
+    }
+}
+
+pub async fn translate_subtitles(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<SubtitleTranslateRequest>,
+) -> Result<Json<SubtitleTranslateResponse>, AppError> {
+    if req.content.trim().is_empty() {
+        return Err(ApiError::BadInput("Subtitle file is empty".to_string()).into());
+    }
+    let format = req
+        .format
+        .unwrap_or_else(|| subtitles::detect_format(&req.content));
+    let file = subtitles::parse(&req.content, format)?;
+
+    let options = translate_options(
+        req.source_lang,
+        req.target_lang,
+        req.formality,
+        req.glossary_id,
+    );
+    state.languages.validate(&options).await?;
+
+    // Cues without words ("♪", "...") are kept as they are
+    let (parts, segments): (Vec<usize>, Vec<ProtectedText>) = file
+        .segments()
+        .into_iter()
+        .enumerate()
+        .filter(|(_, segment)| segment.has_prose())
+        .unzip();
+    let translated = translate_segments(&state, &user, &segments, &options).await?;
+
+    let mut translations = vec![None; file.part_count()];
+    let mut untranslated_cues = Vec::new();
+    for (part, text) in parts.into_iter().zip(translated.texts) {
+        let cue = file.cue_of(part);
+        match text {
+            Ok(text) => translations[part] = Some(text),
+            Err(error) => {
+                warn!("Keeping cue {} untranslated: {}", cue + 1, error);
+                untranslated_cues.push(cue + 1);
+            }
+        }
+    }
+    // A dialogue is kept whole rather than half translated
+    untranslated_cues.dedup();
+    for (part, translation) in translations.iter_mut().enumerate() {
+        if untranslated_cues.contains(&(file.cue_of(part) + 1)) {
+            *translation = None;
+        }
+    }
+
+    Ok(Json(SubtitleTranslateResponse {
+        content: file.write(&translations),
+        format,
+        cues: file.cue_count(),
+        untranslated_cues,
+        detected_source_language: translated.detected_source_language,
+    }))
+}
+
+pub async fn translate_po(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<PoTranslateRequest>,
+) -> Result<Json<PoTranslateResponse>, AppError> {
+    if req.content.trim().is_empty() {
+        return Err(ApiError::BadInput("PO file is empty".to_string()).into());
+    }
+    let catalog = po::parse(&req.content)?;
+
+    let options = translate_options(
+        req.source_lang,
+        req.target_lang,
+        req.formality,
+        req.glossary_id,
+    );
+    state.languages.validate(&options).await?;
+
+    // Messages without words ("%s: %s") are left for the translators
+    let (forms, segments): (Vec<_>, Vec<ProtectedText>) = catalog
+        .segments(req.include_fuzzy)
+        .into_iter()
+        .filter(|(_, _, segment)| segment.has_prose())
+        .map(|(entry, form, segment)| ((entry, form), segment))
+        .unzip();
+
+    // Each msgctxt goes upstream as the context of its entries, so entries
+    // are translated in one group per msgctxt. Catalogs where most entries
+    // have their own msgctxt make many small groups, so several are in flight
+    // at once.
+    let mut groups: HashMap<Option<&str>, Vec<usize>> = HashMap::new();
+    for (i, (entry, _)) in forms.iter().enumerate() {
+        groups.entry(catalog.msgctxt(*entry)).or_default().push(i);
+    }
+    let mut groups: Vec<(TranslateOptions, Vec<usize>)> = groups
+        .into_iter()
+        .map(|(context, members)| {
+            let options = TranslateOptions {
+                context: context.map(str::to_string),
+                ..options.clone()
+            };
+            (options, members)
+        })
+        .collect();
+    groups.sort_unstable_by_key(|(_, members)| members[0]);
+    let results: Vec<_> = stream::iter(groups)
+        .map(|(options, members)| {
+            let group: Vec<ProtectedText> = members.iter().map(|&i| segments[i].clone()).collect();
+            let (state, user) = (&state, &user);
+            async move {
+                let translated = translate_segments(state, user, &group, &options).await;
+                (members, translated)
+            }
+        })
+        .buffered(PO_CONTEXTS_IN_FLIGHT)
+        .collect()
+        .await;
+
+    let mut texts: Vec<Option<Result<String, ApiError>>> = vec![None; forms.len()];
+    let mut detected_source_language = None;
+    for (members, translated) in results {
+        let translated = translated?;
+        for (i, text) in members.into_iter().zip(translated.texts) {
+            texts[i] = Some(text);
+        }
+        detected_source_language = detected_source_language.or(translated.detected_source_language);
+    }
+
+    let mut translations = Vec::with_capacity(forms.len());
+    let mut failed: Vec<usize> = Vec::new();
+    for ((entry, form), text) in forms.into_iter().zip(texts.into_iter().flatten()) {
+        match text {
+            Ok(text) => translations.push(PoTranslation { entry, form, text }),
+            Err(error) => {
+                warn!(
+                    "Keeping \"{}\" untranslated: {}",
+                    catalog.msgid(entry),
+                    error
+                );
+                if !failed.contains(&entry) {
+                    failed.push(entry);
+                }
+            }
+        }
+    }
+    let (content, filled) =
+        catalog.write(&translations, &po::gettext_language(&options.target_lang));
+
+    Ok(Json(PoTranslateResponse {
+        content,
+        translated: filled,
+        failed: failed
+            .into_iter()
+            .map(|entry| catalog.message_id(entry))
+            .collect(),
+        detected_source_language,
+    }))
+}
+
+pub async fn translate_resources(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<ResourceTranslateRequest>,
+) -> Result<Json<ResourceTranslateResponse>, AppError> {
+    if req.content.trim().is_empty() {
+        return Err(ApiError::BadInput("Locale file is empty".to_string()).into());
+    }
+    if req.target_langs.is_empty() {
+        return Err(ApiError::BadInput("No target languages given".to_string()).into());
+    }
+    let format = req
+        .format
+        .unwrap_or_else(|| resources::detect_format(&req.content));
+    let file = resources::parse(&req.content, format)?;
+    let strings = file.strings();
+
+    // Everything is checked before the first language is translated
+    let mut targets = Vec::with_capacity(req.target_langs.len());
+    for target_lang in &req.target_langs {
+        let options = translate_options(
+            req.source_lang.clone(),
+            Some(target_lang.clone()),
+            req.formality.clone(),
+            req.glossary_id.clone(),
+        );
+        state.languages.validate(&options).await?;
+        let existing: HashMap<KeyPath, String> = match req
+            .existing
+            .iter()
+            .find(|(lang, _)| lang.eq_ignore_ascii_case(target_lang))
+        {
+            Some((lang, content)) => resources::parse(content, format)
+                .map_err(|e| {
+                    ApiError::BadInput(format!("Existing {} file: {}", lang, e.message()))
+                })?
+                .strings()
+                .into_iter()
+                .filter(|(_, text)| !text.is_empty())
+                .collect(),
+            None => HashMap::new(),
+        };
+        targets.push((options, existing));
+    }
+
+    let mut response = ResourceTranslateResponse {
+        format,
+        files: Vec::with_capacity(targets.len()),
+        detected_source_language: None,
+    };
+    for (options, mut values) in targets {
+        // Strings without words ("{{count}}") are copied as they are
+        let mut keys = Vec::new();
+        let mut segments = Vec::new();
+        for (path, text) in &strings {
+            if values.contains_key(path) {
+                continue;
+            }
+            let segment = resources::protect(text);
+            if segment.has_prose() {
+                keys.push(path);
+                segments.push(segment);
+            } else {
+                values.insert(path.clone(), text.clone());
+            }
+        }
+        let translated = translate_segments(&state, &user, &segments, &options).await?;
+
+        let mut count = 0;
+        let mut failed = Vec::new();
+        for (path, text) in keys.into_iter().zip(translated.texts) {
+            match text {
+                Ok(text) => {
+                    values.insert(path.clone(), text);
+                    count += 1;
+                }
+                Err(error) => {
+                    let key = resources::key_name(path);
+                    warn!(
+                        "Leaving \"{}\" out of {}: {}",
+                        key, options.target_lang, error
+                    );
+                    failed.push(key);
+                }
+            }
+        }
+        if response.detected_source_language.is_none() {
+            response.detected_source_language = translated.detected_source_language;
+        }
+        response.files.push(TranslatedResource {
+            content: file.write(&values),
+            target_lang: options.target_lang,
+            translated: count,
+            failed,
+        });
+    }
+    Ok(Json(response))
+}
+
+// "en-US" -> "EN"
+fn primary_language(code: &str) -> String {
+    code.split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_uppercase()
+}
+
+// "de-DE" -> "DE" but "en-GB" -> "EN-GB", as the provider lists them
+async fn provider_target_language(state: &AppState, code: &str) -> String {
+    let code = code.replace('_', "-").to_uppercase();
+    let listed = match state.languages.get().await {
+        Ok(languages) => LanguageCatalog::find_target(&languages, &code).is_some(),
+        Err(_) => false,
+    };
+    if listed {
+        code
+    } else {
+        primary_language(&code)
+    }
+}
+
+// "PT-BR" -> "pt-BR", "ZH-HANS" -> "zh-Hans"
+fn xml_language(code: &str) -> String {
+    code.split('-')
+        .enumerate()
+        .map(|(i, part)| match (i, part.len()) {
+            (0, _) => part.to_lowercase(),
+            (_, 4) => part[..1].to_uppercase() + &part[1..].to_lowercase(),
+            _ => part.to_uppercase(),
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+pub async fn translate_xliff(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<XliffTranslateRequest>,
+) -> Result<Json<XliffTranslateResponse>, AppError> {
+    if req.content.trim().is_empty() {
+        return Err(ApiError::BadInput("XLIFF file is empty".to_string()).into());
+    }
+    let file = xliff::parse(&req.content)?;
+
+    let source_lang = req
+        .source_lang
+        .filter(|s| !s.is_empty())
+        .or_else(|| file.source_lang.as_deref().map(primary_language));
+    let target_lang = match req.target_lang.filter(|t| !t.is_empty()) {
+        Some(target_lang) => target_lang,
+        None => match &file.target_lang {
+            Some(target_lang) => provider_target_language(&state, target_lang).await,
+            None => {
+                return Err(ApiError::BadInput(
+                    "The file does not name a target language; set target_lang".to_string(),
+                )
+                .into())
+            }
+        },
+    };
+    let options = translate_options(
+        source_lang,
+        Some(target_lang),
+        req.formality,
+        req.glossary_id,
+    );
+    state.languages.validate(&options).await?;
+
+    // Units without words ("{1}", "42") are left for the translators
+    let all_segments = file.segments();
+    let mut translations = vec![None; all_segments.len()];
+    let (units, segments): (Vec<usize>, Vec<ProtectedText>) = all_segments
+        .into_iter()
+        .enumerate()
+        .filter(|(_, segment)| segment.has_prose())
+        .unzip();
+    let translated = translate_segments(&state, &user, &segments, &options).await?;
+
+    let mut count = 0;
+    let mut failed = Vec::new();
+    for (unit, text) in units.into_iter().zip(translated.texts) {
+        match text.and_then(|text| {
+            markup::check_tags(file.source(unit), &text, TagHandling::Xml).map(|()| text)
+        }) {
+            Ok(text) => {
+                translations[unit] = Some(text);
+                count += 1;
+            }
+            Err(error) => {
+                warn!(
+                    "Leaving unit {} untranslated: {}",
+                    file.unit_id(unit),
+                    error
+                );
+                failed.push(file.unit_id(unit).to_string());
+            }
+        }
+    }
+
+    Ok(Json(XliffTranslateResponse {
+        content: file.write(&translations, &xml_language(&options.target_lang)),
+        version: file.version.clone(),
+        translated: count,
+        failed,
+        detected_source_language: translated.detected_source_language,
+    }))
+}
+
+pub async fn translate_fluent(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<FluentTranslateRequest>,
+) -> Result<Json<FluentTranslateResponse>, AppError> {
+    if req.content.trim().is_empty() {
+        return Err(ApiError::BadInput("Fluent file is empty".to_string()).into());
+    }
+    let file = fluent::parse(&req.content)?;
+
+    let options = translate_options(
+        req.source_lang,
+        req.target_lang,
+        req.formality,
+        req.glossary_id,
+    );
+    state.languages.validate(&options).await?;
+
+    // Patterns that are only placeables ("{ $count }") are kept as they are
+    let all_segments = file.segments();
+    let mut texts = vec![None; all_segments.len()];
+    let (patterns, segments): (Vec<usize>, Vec<ProtectedText>) = all_segments
+        .into_iter()
+        .enumerate()
+        .filter(|(_, segment)| segment.has_prose())
+        .unzip();
+    let translated = translate_segments(&state, &user, &segments, &options).await?;
+
+    let mut failed: Vec<usize> = Vec::new();
+    for (pattern, text) in patterns.into_iter().zip(translated.texts) {
+        match text {
+            Ok(text) => texts[pattern] = Some(text),
+            Err(error) => {
+                let message = file.message_of(pattern);
+                warn!(
+                    "Keeping message {} untranslated: {}",
+                    file.message_id(message),
+                    error
+                );
+                if !failed.contains(&message) {
+                    failed.push(message);
+                }
+            }
+        }
+    }
+
+    // A message is only rewritten when all of its patterns were translated
+    let mut rewritten = vec![None; file.message_count()];
+    let mut count = 0;
+    for (message, slot) in rewritten.iter_mut().enumerate() {
+        if failed.contains(&message) || file.patterns_of(message).all(|p| texts[p].is_none()) {
+            continue;
+        }
+        match file.rewrite(message, &texts) {
+            Ok(source) => {
+                *slot = Some(source);
+                count += 1;
+            }
+            Err(error) => {
+                warn!(
+                    "Keeping message {} untranslated: {}",
+                    file.message_id(message),
+                    error
+                );
+                failed.push(message);
+            }
+        }
+    }
+    failed.sort_unstable();
+
+    Ok(Json(FluentTranslateResponse {
+        content: file.write(&rewritten)?,
+        translated: count,
+        failed: failed
+            .into_iter()
+            .map(|message| file.message_id(message).to_string())
+            .collect(),
+        detected_source_language: translated.detected_source_language,
+    }))
+}
@@ -0,0 +1,493 @@
+use crate::placeholders::{Piece, ProtectedText};
+use common::ApiError;
+use fluent_syntax::ast::{
+    Entry, Expression, Identifier, InlineExpression, Message, Pattern, PatternElement, Resource,
+    Term, VariantKey,
+};
+use fluent_syntax::parser::{self, ParserError};
+use fluent_syntax::serializer;
+use std::ops::Range;
+
+/// A Fluent resource (.ftl). Message values and attributes are translated with
+/// their placeables masked; terms, comments and the source of untranslated
+/// messages are written back as they were.
+pub struct FluentFile<'a> {
+    source: &'a str,
+    line_ending: &'static str,
+    messages: Vec<FluentMessage>,
+    /// The value and attributes of every message, in order
+    patterns: Vec<FluentPattern>,
+}
+
+struct FluentMessage {
+    id: String,
+    /// Lines of the message in the source, without its comment
+    range: Range<usize>,
+    patterns: Range<usize>,
+}
+
+struct FluentPattern {
+    message: usize,
+    attribute: Option<String>,
+    /// The pattern as Fluent source, with selectors on their own lines
+    original: String,
+    segment: ProtectedText,
+    shape: Vec<String>,
+}
+
+fn parse_errors(source: &str, errors: &[ParserError]) -> ApiError {
+    let messages: Vec<String> = errors
+        .iter()
+        .take(3)
+        .map(|error| {
+            let line = source[..error.pos.start.min(source.len())]
+                .matches('\n')
+                .count();
+            format!("line {}: {}", line + 1, error.kind)
+        })
+        .collect();
+    ApiError::BadInput(format!("Invalid Fluent syntax: {}", messages.join("; ")))
+}
+
+// `expression` as it is written inside a placeable
+fn serialize_expression(expression: &Expression<&str>) -> String {
+    let resource = Resource {
+        body: vec![Entry::Message(Message {
+            id: Identifier { name: "x" },
+            value: Some(Pattern {
+                elements: vec![PatternElement::Placeable {
+                    expression: expression.clone(),
+                }],
+            }),
+            attributes: Vec::new(),
+            comment: None,
+        })],
+    };
+    let serialized = serializer::serialize(&resource);
+    serialized
+        .trim_end()
+        .trim_start_matches("x = ")
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .trim()
+        .to_string()
+}
+
+// Characters the translation may not use as text: braces, and "[", "*" and
+// "." at the start of a line. They are written as string literals.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut line_start = true;
+    for c in text.chars() {
+        match c {
+            '{' | '}' => escaped.push_str(&format!("{{ \"{}\" }}", c)),
+            '[' | '*' | '.' if line_start => escaped.push_str(&format!("{{ \"{}\" }}", c)),
+            c => escaped.push(c),
+        }
+        line_start = c == '\n' || (line_start && c == ' ');
+    }
+    escaped
+}
+
+fn variant_key(key: &VariantKey<&str>) -> String {
+    match key {
+        VariantKey::Identifier { name } => name.to_string(),
+        VariantKey::NumberLiteral { value } => value.to_string(),
+    }
+}
+
+// Text, and placeholders for everything else: placeables, and the selector,
+// variant keys and closing brace of a select expression
+fn pieces(pattern: &Pattern<&str>, pieces: &mut Vec<Piece>) {
+    for element in &pattern.elements {
+        match element {
+            PatternElement::TextElement { value } => pieces.push(Piece::Text(value.to_string())),
+            PatternElement::Placeable {
+                expression: Expression::Select { selector, variants },
+            } => {
+                let selector = serialize_expression(&Expression::Inline(selector.clone()));
+                pieces.push(Piece::Placeholder(format!("{{ {} ->", selector)));
+                for variant in variants {
+                    let marker = if variant.default { "*" } else { "" };
+                    pieces.push(Piece::Placeholder(format!(
+                        "\n{}[{}] ",
+                        marker,
+                        variant_key(&variant.key)
+                    )));
+                    self::pieces(&variant.value, pieces);
+                }
+                pieces.push(Piece::Placeholder("\n}".to_string()));
+            }
+            PatternElement::Placeable { expression } => pieces.push(Piece::Placeholder(format!(
+                "{{ {} }}",
+                serialize_expression(expression)
+            ))),
+        }
+    }
+}
+
+// The placeables of `pattern`, in any order, and the variants of each select
+// expression with their own placeables. String literals are left out, as the
+// translation may need others.
+fn shape(pattern: &Pattern<&str>) -> Vec<String> {
+    let mut shape: Vec<String> = pattern
+        .elements
+        .iter()
+        .filter_map(|element| match element {
+            PatternElement::TextElement { .. }
+            | PatternElement::Placeable {
+                expression: Expression::Inline(InlineExpression::StringLiteral { .. }),
+            } => None,
+            PatternElement::Placeable {
+                expression: Expression::Select { selector, variants },
+            } => {
+                let variants: Vec<String> = variants
+                    .iter()
+                    .map(|variant| {
+                        let marker = if variant.default { "*" } else { "" };
+                        format!(
+                            "{}[{}] {:?}",
+                            marker,
+                            variant_key(&variant.key),
+                            shape(&variant.value)
+                        )
+                    })
+                    .collect();
+                Some(format!(
+                    "{} -> {}",
+                    serialize_expression(&Expression::Inline(selector.clone())),
+                    variants.join(" ")
+                ))
+            }
+            PatternElement::Placeable { expression } => Some(serialize_expression(expression)),
+        })
+        .collect();
+    shape.sort();
+    shape
+}
+
+fn pattern(message: usize, attribute: Option<String>, value: &Pattern<&str>) -> FluentPattern {
+    let mut parts = Vec::new();
+    pieces(value, &mut parts);
+    let original = parts
+        .iter()
+        .map(|piece| match piece {
+            Piece::Text(text) | Piece::Placeholder(text) => text.as_str(),
+        })
+        .collect();
+    FluentPattern {
+        message,
+        attribute,
+        original,
+        segment: ProtectedText::from_pieces(parts).escaping(escape),
+        shape: shape(value),
+    }
+}
+
+// Each pattern and its attribute, in the order of `FluentFile::patterns`
+fn message_patterns<'m>(
+    message: &'m Message<&str>,
+) -> Vec<(Option<&'m str>, &'m Pattern<&'m str>)> {
+    message
+        .value
+        .iter()
+        .map(|value| (None, value))
+        .chain(
+            message
+                .attributes
+                .iter()
+                .map(|attribute| (Some(attribute.id.name), &attribute.value)),
+        )
+        .collect()
+}
+
+// Offset of `slice` in `source`. The parser borrows every name and line of
+// text from the source, so this is where it read them.
+fn offset(source: &str, slice: &str) -> usize {
+    let offset = (slice.as_ptr() as usize).wrapping_sub(source.as_ptr() as usize);
+    debug_assert!(offset <= source.len(), "slice outside of the source");
+    offset.min(source.len())
+}
+
+// Start of the line holding `offset`
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+// Where `entry` starts in `source`, including a comment attached to it
+fn entry_start(source: &str, entry: &Entry<&str>) -> usize {
+    let first = match entry {
+        Entry::Message(Message {
+            comment: Some(comment),
+            ..
+        })
+        | Entry::Term(Term {
+            comment: Some(comment),
+            ..
+        })
+        | Entry::Comment(comment)
+        | Entry::GroupComment(comment)
+        | Entry::ResourceComment(comment) => comment.content.first().copied(),
+        Entry::Message(message) => Some(message.id.name),
+        Entry::Term(term) => Some(term.id.name),
+        Entry::Junk { content } => Some(*content),
+    };
+    first.map_or(source.len(), |first| {
+        line_start(source, offset(source, first))
+    })
+}
+
+// Byte ranges of the messages in `resource`, from the line with the
+// identifier up to the end of the last line before the next entry that is
+// not blank
+fn message_ranges(source: &str, resource: &Resource<&str>) -> Vec<Range<usize>> {
+    let starts: Vec<usize> = resource
+        .body
+        .iter()
+        .map(|entry| entry_start(source, entry))
+        .chain([source.len()])
+        .collect();
+    resource
+        .body
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| match entry {
+            Entry::Message(message) => Some((offset(source, message.id.name), starts[i + 1])),
+            _ => None,
+        })
+        .map(|(start, next)| {
+            let end = start + source[start..next].trim_end().len();
+            let end = source[end..next].find('\n').map_or(next, |i| end + i + 1);
+            start..end
+        })
+        .collect()
+}
+
+pub fn parse(source: &str) -> Result<FluentFile<'_>, ApiError> {
+    let resource = parser::parse(source).map_err(|(_, errors)| parse_errors(source, &errors))?;
+    let ranges = message_ranges(source, &resource);
+
+    let mut file = FluentFile {
+        source,
+        line_ending: if source.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        },
+        messages: Vec::new(),
+        patterns: Vec::new(),
+    };
+    let messages = resource.body.iter().filter_map(|entry| match entry {
+        Entry::Message(message) => Some(message),
+        _ => None,
+    });
+    for (message, range) in messages.zip(ranges) {
+        let index = file.messages.len();
+        let start = file.patterns.len();
+        for (attribute, value) in message_patterns(message) {
+            file.patterns
+                .push(pattern(index, attribute.map(str::to_string), value));
+        }
+        file.messages.push(FluentMessage {
+            id: message.id.name.to_string(),
+            range,
+            patterns: start..file.patterns.len(),
+        });
+    }
+    Ok(file)
+}
+
+// `text` on its own lines, each indented by `indent`
+fn block(text: &str, indent: &str) -> String {
+    text.lines()
+        .map(|line| format!("\n{}{}", indent, line))
+        .collect()
+}
+
+impl FluentFile<'_> {
+    pub fn message_count(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn message_id(&self, message: usize) -> &str {
+        &self.messages[message].id
+    }
+
+    /// Message a pattern belongs to
+    pub fn message_of(&self, pattern: usize) -> usize {
+        self.patterns[pattern].message
+    }
+
+    /// Patterns of a message: its value, then its attributes
+    pub fn patterns_of(&self, message: usize) -> Range<usize> {
+        self.messages[message].patterns.clone()
+    }
+
+    /// Every message value and attribute, with placeables and select syntax
+    /// masked
+    pub fn segments(&self) -> Vec<ProtectedText> {
+        self.patterns
+            .iter()
+            .map(|pattern| pattern.segment.clone())
+            .collect()
+    }
+
+    /// The message written with `texts`, given per pattern of the file with
+    /// `None` for a pattern kept as it was. Fails when the result does not
+    /// parse back into the same message with the same placeables and variants.
+    pub fn rewrite(&self, message: usize, texts: &[Option<String>]) -> Result<String, ApiError> {
+        let entry = &self.messages[message];
+        let mut source = format!("{} =", entry.id);
+        for index in entry.patterns.clone() {
+            let pattern = &self.patterns[index];
+            let text = texts[index].as_deref().unwrap_or(&pattern.original);
+            match &pattern.attribute {
+                None => source.push_str(&block(text, "    ")),
+                Some(attribute) => {
+                    source.push_str(&format!("\n    .{} =", attribute));
+                    source.push_str(&block(text, "        "));
+                }
+            }
+        }
+        source.push('\n');
+
+        let broken = |reason: String| {
+            ApiError::OutputCheckFailed(format!(
+                "The translation of \"{}\" is no longer valid Fluent: {}",
+                entry.id, reason
+            ))
+        };
+        let resource =
+            parser::parse(source.as_str()).map_err(|(_, errors)| match errors.first() {
+                Some(error) => broken(error.kind.to_string()),
+                None => broken("it does not parse".to_string()),
+            })?;
+        let parsed = match resource.body.as_slice() {
+            [Entry::Message(parsed)] if parsed.id.name == entry.id => parsed,
+            _ => return Err(broken("it no longer reads as one message".to_string())),
+        };
+        let patterns = message_patterns(parsed);
+        let expected = &self.patterns[entry.patterns.clone()];
+        if patterns.len() != expected.len() {
+            return Err(broken("its attributes changed".to_string()));
+        }
+        for ((attribute, value), pattern) in patterns.into_iter().zip(expected) {
+            if attribute != pattern.attribute.as_deref() {
+                return Err(broken("its attributes changed".to_string()));
+            }
+            if shape(value) != pattern.shape {
+                return Err(broken(
+                    "its placeables or variants do not match the source".to_string(),
+                ));
+            }
+        }
+        Ok(serializer::serialize(&resource))
+    }
+
+    /// The file with each message that has a rewritten source, see
+    /// [`Self::rewrite`], replaced by it
+    pub fn write(&self, rewritten: &[Option<String>]) -> Result<String, ApiError> {
+        let mut output = String::with_capacity(self.source.len());
+        let mut pos = 0;
+        for (message, text) in self.messages.iter().zip(rewritten) {
+            let Some(text) = text else {
+                continue;
+            };
+            output.push_str(&self.source[pos..message.range.start]);
+            let text = if self.source[..message.range.end].ends_with('\n') {
+                text.clone()
+            } else {
+                text.trim_end_matches('\n').to_string()
+            };
+            output.push_str(&text.replace('\n', self.line_ending));
+            pos = message.range.end;
+        }
+        output.push_str(&self.source[pos..]);
+
+        // Each message was checked; this catches anything they did to their
+        // surroundings
+        parser::parse(output.as_str()).map_err(|(_, errors)| {
+            ApiError::OutputCheckFailed(format!(
+                "The translated file no longer parses as Fluent: {}",
+                errors
+                    .first()
+                    .map(|error| error.kind.to_string())
+                    .unwrap_or_default()
+            ))
+        })?;
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Translates every message into upper case, as the upstream would with
+    // the placeables masked
+    fn shout(source: &str) -> String {
+        let file = parse(source).unwrap();
+        let texts: Vec<Option<String>> = file
+            .segments()
+            .iter()
+            .map(|segment| {
+                segment
+                    .has_prose()
+                    .then(|| segment.restore(&segment.text.to_uppercase()).unwrap())
+            })
+            .collect();
+        let rewritten: Vec<Option<String>> = (0..file.message_count())
+            .map(|message| Some(file.rewrite(message, &texts).unwrap()))
+            .collect();
+        file.write(&rewritten).unwrap()
+    }
+
+    #[test]
+    fn select_closed_at_line_start() {
+        let source = "emails = { $n ->\n    [one] One email\n   *[other] { $n } emails\n}\n\
+                      next = Next\n";
+        assert_eq!(
+            shout(source),
+            "emails =\n    { $n ->\n        [one] ONE EMAIL\n       *[other] { $n } EMAILS\n    }\n\
+             next = NEXT\n"
+        );
+    }
+
+    #[test]
+    fn select_round_trip() {
+        let source = "# Shown in the inbox\n\
+                      unread =\n    { $count ->\n        [one] One new message\n       *[other] { $count } new messages\n    }\n";
+        assert_eq!(
+            shout(source),
+            "# Shown in the inbox\n\
+             unread =\n    { $count ->\n        [one] ONE NEW MESSAGE\n       *[other] { $count } NEW MESSAGES\n    }\n"
+        );
+    }
+
+    #[test]
+    fn attributes_are_translated() {
+        let source = "login = Sign in\n    .title = Sign in to { $site }\n    .accesskey = S\n";
+        assert_eq!(
+            shout(source),
+            "login = SIGN IN\n    .title = SIGN IN TO { $site }\n    .accesskey = S\n"
+        );
+    }
+
+    #[test]
+    fn terms_are_kept() {
+        let source = "-brand = Firefox\n\nwelcome = Welcome to { -brand }\n";
+        assert_eq!(
+            shout(source),
+            "-brand = Firefox\n\nwelcome = WELCOME TO { -brand }\n"
+        );
+    }
+
+    #[test]
+    fn line_endings_are_kept() {
+        let source = "hello = Hello\r\n\r\nbye = Goodbye\r\n    .title = See you\r\n";
+        assert_eq!(
+            shout(source),
+            "hello = HELLO\r\n\r\nbye = GOODBYE\r\n    .title = SEE YOU\r\n"
+        );
+    }
+}
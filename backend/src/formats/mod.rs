@@ -0,0 +1,9 @@
+//! Parsers and writers for the file formats translated by the `files` endpoints.
+//! Each splits a file into segments for the upstream and writes the file back
+//! with the translations in place, leaving everything else as it was.
+
+pub mod fluent;
+pub mod po;
+pub mod resources;
+pub mod subtitles;
+pub mod xliff;
@@ -0,0 +1,489 @@
+use crate::placeholders::{self, ProtectedText};
+use common::{ApiError, PoMessageId};
+use regex::Regex;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// A gettext catalog (.po or .pot). Lines are kept as read, so comments,
+/// references and the wrapping of untouched entries survive; only the
+/// `msgstr` lines and flags of translated entries are rewritten.
+pub struct PoCatalog {
+    lines: Vec<String>,
+    line_ending: &'static str,
+    trailing_newline: bool,
+    entries: Vec<PoEntry>,
+    /// From the header's Plural-Forms, when it is filled in
+    nplurals: Option<usize>,
+    /// The header line holding an empty "Language: " field
+    language_line: Option<usize>,
+}
+
+#[derive(Default)]
+struct PoEntry {
+    /// First line of `msgctxt` or `msgid`, where a flags line is inserted
+    keyword_line: Option<usize>,
+    /// First "#|" line, which must come after the flags
+    previous_line: Option<usize>,
+    flags_line: Option<usize>,
+    flags: Vec<String>,
+    msgctxt: Option<String>,
+    msgid: Option<String>,
+    msgid_plural: Option<String>,
+    /// Lines and value of `msgstr`, or of each `msgstr[n]` in order
+    msgstrs: Vec<(Range<usize>, String)>,
+}
+
+/// Which form of an entry a segment translates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PoForm {
+    Singular,
+    Plural,
+}
+
+// The string of the line being continued by "..." lines
+enum Field {
+    Msgctxt,
+    Msgid,
+    MsgidPlural,
+    Msgstr,
+}
+
+fn keyword_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(msgctxt|msgid_plural|msgid|msgstr(?:\[\d+\])?)\s+(.*)$")
+            .expect("valid keyword pattern")
+    })
+}
+
+fn nplurals_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"nplurals\s*=\s*(\d+)").expect("valid nplurals pattern"))
+}
+
+fn parse_error(line: usize, message: &str) -> ApiError {
+    ApiError::BadInput(format!("Line {}: {}", line + 1, message))
+}
+
+// A C-style quoted string, e.g. "Line\n"
+fn unquote(quoted: &str, line: usize) -> Result<String, ApiError> {
+    let inner = quoted
+        .trim()
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .ok_or_else(|| parse_error(line, "expected a quoted string"))?;
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('a') => value.push('\u{7}'),
+            Some('b') => value.push('\u{8}'),
+            Some('f') => value.push('\u{c}'),
+            Some('v') => value.push('\u{b}'),
+            Some(c @ ('"' | '\\' | '\'' | '?')) => value.push(c),
+            _ => return Err(parse_error(line, "invalid escape sequence")),
+        }
+    }
+    Ok(value)
+}
+
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// `msgstr "..."`, or one line per text line when there are several
+fn format_field(keyword: &str, value: &str) -> Vec<String> {
+    let parts: Vec<&str> = value.split_inclusive('\n').collect();
+    if parts.len() <= 1 {
+        return vec![format!("{} {}", keyword, quote(value))];
+    }
+    let mut lines = vec![format!("{} \"\"", keyword)];
+    lines.extend(parts.into_iter().map(quote));
+    lines
+}
+
+impl PoEntry {
+    fn is_header(&self) -> bool {
+        self.msgctxt.is_none() && self.msgid.as_deref() == Some("")
+    }
+
+    fn is_fuzzy(&self) -> bool {
+        self.flags.iter().any(|flag| flag == "fuzzy")
+    }
+
+    fn is_translated(&self) -> bool {
+        self.msgstrs.iter().any(|(_, value)| !value.is_empty())
+    }
+}
+
+pub fn parse(content: &str) -> Result<PoCatalog, ApiError> {
+    let content = content.trim_start_matches('\u{feff}');
+    let lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let mut entries = Vec::new();
+    let mut entry = PoEntry::default();
+    let mut field: Option<Field> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        // A comment or msgctxt/msgid after a msgstr starts the next entry,
+        // with or without a blank line in between
+        let starts_entry = trimmed.is_empty()
+            || trimmed.starts_with('#')
+            || trimmed.starts_with("msgctxt")
+            || (trimmed.starts_with("msgid") && !trimmed.starts_with("msgid_plural"));
+        if starts_entry && !entry.msgstrs.is_empty() {
+            entries.push(std::mem::take(&mut entry));
+        }
+
+        if trimmed.is_empty() {
+            // Comments of an obsolete entry must not stick to the next one
+            if entry.msgid.is_none() {
+                entry = PoEntry::default();
+            }
+            field = None;
+        } else if let Some(flags) = trimmed.strip_prefix("#,") {
+            entry.flags_line = Some(i);
+            entry.flags = flags
+                .split(',')
+                .map(|flag| flag.trim().to_string())
+                .filter(|flag| !flag.is_empty())
+                .collect();
+            field = None;
+        } else if trimmed.starts_with("#|") {
+            entry.previous_line.get_or_insert(i);
+            field = None;
+        } else if trimmed.starts_with('#') {
+            // Translator and extracted comments, references, obsolete entries
+            field = None;
+        } else if let Some(caps) = keyword_pattern().captures(trimmed) {
+            let value = unquote(&caps[2], i)?;
+            let keyword = &caps[1];
+            if keyword == "msgctxt" || keyword == "msgid" {
+                entry.keyword_line.get_or_insert(i);
+            }
+            field = Some(match keyword {
+                "msgctxt" => {
+                    entry.msgctxt = Some(value);
+                    Field::Msgctxt
+                }
+                "msgid" => {
+                    entry.msgid = Some(value);
+                    Field::Msgid
+                }
+                "msgid_plural" => {
+                    entry.msgid_plural = Some(value);
+                    Field::MsgidPlural
+                }
+                _ => {
+                    entry.msgstrs.push((i..i + 1, value));
+                    Field::Msgstr
+                }
+            });
+        } else if trimmed.starts_with('"') {
+            let value = unquote(trimmed, i)?;
+            let target = match field {
+                Some(Field::Msgctxt) => entry.msgctxt.as_mut(),
+                Some(Field::Msgid) => entry.msgid.as_mut(),
+                Some(Field::MsgidPlural) => entry.msgid_plural.as_mut(),
+                Some(Field::Msgstr) => entry.msgstrs.last_mut().map(|(range, value)| {
+                    range.end = i + 1;
+                    value
+                }),
+                None => None,
+            };
+            target
+                .ok_or_else(|| parse_error(i, "string outside of an entry"))?
+                .push_str(&value);
+        } else {
+            return Err(parse_error(i, "not a valid PO line"));
+        }
+    }
+    if !entry.msgstrs.is_empty() {
+        entries.push(entry);
+    }
+    if let Some(entry) = entries.iter().find(|e| e.msgid.is_none()) {
+        return Err(parse_error(
+            entry.msgstrs[0].0.start,
+            "msgstr without msgid",
+        ));
+    }
+    if entries.iter().all(PoEntry::is_header) {
+        return Err(ApiError::BadInput(
+            "No messages found in the PO file".to_string(),
+        ));
+    }
+
+    let header = entries.iter().find(|e| e.is_header());
+    let nplurals = header
+        .and_then(|h| nplurals_pattern().captures(&h.msgstrs.first()?.1))
+        .and_then(|caps| caps[1].parse().ok())
+        .filter(|&n| n > 0);
+    let language_line = header.and_then(|h| {
+        let (range, _) = h.msgstrs.first()?;
+        range
+            .clone()
+            .find(|&i| lines[i].trim() == "\"Language: \\n\"")
+    });
+
+    Ok(PoCatalog {
+        line_ending: if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        },
+        trailing_newline: content.ends_with('\n'),
+        lines,
+        entries,
+        nplurals,
+        language_line,
+    })
+}
+
+/// A translation for one form of an entry, see [`PoCatalog::segments`]
+pub struct PoTranslation {
+    pub entry: usize,
+    pub form: PoForm,
+    pub text: String,
+}
+
+impl PoCatalog {
+    /// The entries to translate: untranslated ones, and with `include_fuzzy`
+    /// also fuzzy ones. Each gives its msgid, and msgid_plural if it has one,
+    /// with placeholders masked.
+    pub fn segments(&self, include_fuzzy: bool) -> Vec<(usize, PoForm, ProtectedText)> {
+        let mut segments = Vec::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            if entry.is_header() || (entry.is_translated() && !(include_fuzzy && entry.is_fuzzy()))
+            {
+                continue;
+            }
+            if let Some(msgid) = &entry.msgid {
+                segments.push((i, PoForm::Singular, placeholders::protect(msgid)));
+            }
+            if let Some(msgid_plural) = &entry.msgid_plural {
+                segments.push((i, PoForm::Plural, placeholders::protect(msgid_plural)));
+            }
+        }
+        segments
+    }
+
+    pub fn msgid(&self, entry: usize) -> &str {
+        self.entries[entry].msgid.as_deref().unwrap_or_default()
+    }
+
+    pub fn msgctxt(&self, entry: usize) -> Option<&str> {
+        self.entries[entry].msgctxt.as_deref()
+    }
+
+    pub fn message_id(&self, entry: usize) -> PoMessageId {
+        PoMessageId {
+            msgctxt: self.msgctxt(entry).map(str::to_string),
+            msgid: self.msgid(entry).to_string(),
+        }
+    }
+
+    /// The catalog with the given entries filled in and marked fuzzy. Plural
+    /// entries get the singular in `msgstr[0]` and the plural in the others,
+    /// as many as the header's Plural-Forms asks for. Entries missing a form
+    /// are left as they were. An empty Language header is set to `language`.
+    /// Also returns the number of entries filled in.
+    pub fn write(&self, translations: &[PoTranslation], language: &str) -> (String, usize) {
+        // Replacements of line ranges, applied in order; empty ranges insert
+        let mut edits: Vec<(Range<usize>, Vec<String>)> = Vec::new();
+        let translations: HashMap<(usize, PoForm), &str> = translations
+            .iter()
+            .map(|t| ((t.entry, t.form), t.text.as_str()))
+            .collect();
+        let mut filled = 0;
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            let form = |form: PoForm| translations.get(&(i, form)).copied();
+            let Some(singular) = form(PoForm::Singular) else {
+                continue;
+            };
+            let plural = form(PoForm::Plural);
+            if entry.msgid_plural.is_some() && plural.is_none() {
+                continue;
+            }
+            filled += 1;
+
+            if !entry.is_fuzzy() {
+                let mut flags = vec!["fuzzy".to_string()];
+                flags.extend(entry.flags.iter().cloned());
+                let line = format!("#, {}", flags.join(", "));
+                match (entry.flags_line, entry.previous_line.or(entry.keyword_line)) {
+                    (Some(flags_line), _) => edits.push((flags_line..flags_line + 1, vec![line])),
+                    (None, Some(before)) => edits.push((before..before, vec![line])),
+                    (None, None) => {}
+                }
+            }
+
+            let Some(plural) = plural else {
+                if let Some((range, _)) = entry.msgstrs.first() {
+                    edits.push((range.clone(), format_field("msgstr", singular)));
+                }
+                continue;
+            };
+            let count = self.nplurals.unwrap_or(entry.msgstrs.len()).max(1);
+            let text = |n: usize| {
+                if n == 0 && count > 1 {
+                    singular
+                } else {
+                    plural
+                }
+            };
+            for (n, (range, _)) in entry.msgstrs.iter().enumerate().take(count) {
+                edits.push((
+                    range.clone(),
+                    format_field(&format!("msgstr[{}]", n), text(n)),
+                ));
+            }
+            if let Some((last, _)) = entry.msgstrs.last() {
+                let added: Vec<String> = (entry.msgstrs.len()..count)
+                    .flat_map(|n| format_field(&format!("msgstr[{}]", n), text(n)))
+                    .collect();
+                if !added.is_empty() {
+                    edits.push((last.end..last.end, added));
+                }
+            }
+        }
+        if let Some(line) = self.language_line {
+            if !translations.is_empty() {
+                edits.push((
+                    line..line + 1,
+                    vec![format!("\"Language: {}\\n\"", language)],
+                ));
+            }
+        }
+        edits.sort_by_key(|(range, _)| (range.start, range.end));
+
+        let mut output: Vec<&str> = Vec::with_capacity(self.lines.len());
+        let mut edits = edits.iter().peekable();
+        let mut i = 0;
+        loop {
+            let mut replaced_to = i;
+            while let Some((range, lines)) = edits.next_if(|(range, _)| range.start == i) {
+                output.extend(lines.iter().map(String::as_str));
+                replaced_to = replaced_to.max(range.end);
+            }
+            if replaced_to > i {
+                i = replaced_to;
+                continue;
+            }
+            match self.lines.get(i) {
+                Some(line) => output.push(line),
+                None => break,
+            }
+            i += 1;
+        }
+
+        let mut content = output.join(self.line_ending);
+        if self.trailing_newline {
+            content.push_str(self.line_ending);
+        }
+        (content, filled)
+    }
+}
+
+/// "PT-BR" -> "pt_BR", the gettext spelling of a language code
+pub fn gettext_language(code: &str) -> String {
+    match code.split_once('-') {
+        Some((language, region)) => {
+            format!("{}_{}", language.to_lowercase(), region.to_uppercase())
+        }
+        None => code.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translation(entry: usize, text: &str) -> PoTranslation {
+        PoTranslation {
+            entry,
+            form: PoForm::Singular,
+            text: text.to_string(),
+        }
+    }
+
+    fn msgids(catalog: &PoCatalog) -> Vec<&str> {
+        catalog
+            .segments(false)
+            .iter()
+            .map(|(entry, _, _)| catalog.msgid(*entry))
+            .collect()
+    }
+
+    #[test]
+    fn obsolete_entries_are_kept_and_not_translated() {
+        let content = "msgid \"Save\"\nmsgstr \"\"\n\n\
+                       #~ msgid \"Old\"\n#~ msgstr \"Alt\"\n\n\
+                       msgid \"Load\"\nmsgstr \"\"\n";
+        let catalog = parse(content).unwrap();
+        assert_eq!(msgids(&catalog), ["Save", "Load"]);
+
+        let (output, filled) = catalog.write(&[translation(1, "Laden")], "de");
+        assert_eq!(filled, 1);
+        assert_eq!(
+            output,
+            "msgid \"Save\"\nmsgstr \"\"\n\n\
+             #~ msgid \"Old\"\n#~ msgstr \"Alt\"\n\n\
+             #, fuzzy\nmsgid \"Load\"\nmsgstr \"Laden\"\n"
+        );
+    }
+
+    #[test]
+    fn multiline_msgstr_is_replaced_whole() {
+        let content = "#, fuzzy, c-format\nmsgid \"\"\n\"First %s\\n\"\n\"Second\"\n\
+                       msgstr \"\"\n\"Erste\\n\"\n\"Zweite\"\n";
+        let catalog = parse(content).unwrap();
+        assert!(catalog.segments(false).is_empty());
+        let segments = catalog.segments(true);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(catalog.msgid(0), "First %s\nSecond");
+
+        let (output, _) = catalog.write(&[translation(0, "Erste %s\nZweite neu")], "de");
+        assert_eq!(
+            output,
+            "#, fuzzy, c-format\nmsgid \"\"\n\"First %s\\n\"\n\"Second\"\n\
+             msgstr \"\"\n\"Erste %s\\n\"\n\"Zweite neu\"\n"
+        );
+    }
+
+    #[test]
+    fn entry_at_end_of_file_without_newline() {
+        let content = "msgctxt \"menu\"\nmsgid \"Open\"\nmsgstr \"\"";
+        let catalog = parse(content).unwrap();
+        assert_eq!(msgids(&catalog), ["Open"]);
+        assert_eq!(catalog.msgctxt(0), Some("menu"));
+
+        let (output, filled) = catalog.write(&[translation(0, "Öffnen")], "de");
+        assert_eq!(filled, 1);
+        assert_eq!(
+            output,
+            "#, fuzzy\nmsgctxt \"menu\"\nmsgid \"Open\"\nmsgstr \"Öffnen\""
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::placeholders::{self, Piece, ProtectedText};
+use common::{ApiError, ResourceFormat};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// A JSON or YAML locale file, such as i18next's. Only string values are
+/// translated; keys, their order and every other value are written back as
+/// they were. YAML comments and quoting are not kept.
+pub struct ResourceFile {
+    document: Document,
+    /// Indentation of nested JSON keys
+    indent: String,
+    line_ending: &'static str,
+    trailing_newline: bool,
+    /// Whether the YAML starts with a "---" document marker
+    document_marker: bool,
+}
+
+#[derive(Clone)]
+enum Document {
+    Json(serde_json::Value),
+    Yaml(serde_norway::Value),
+}
+
+/// Keys leading to a string value, with array elements numbered from 0
+pub type KeyPath = Vec<String>;
+
+// Called with each string value; `None` leaves the value out
+type Fill<'a> = dyn FnMut(&KeyPath, &str) -> Option<String> + 'a;
+
+// $t(other.key) nesting and <strong>, </1>, <br/> tags of react-i18next
+fn markup_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\$t\([^()]*\)|</?[A-Za-z0-9][^<>]*>").expect("valid markup pattern")
+    })
+}
+
+/// "home.title" for `["home", "title"]`
+pub fn key_name(path: &KeyPath) -> String {
+    path.join(".")
+}
+
+pub fn detect_format(content: &str) -> ResourceFormat {
+    if content
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .starts_with('{')
+    {
+        ResourceFormat::Json
+    } else {
+        ResourceFormat::Yaml
+    }
+}
+
+pub fn parse(content: &str, format: ResourceFormat) -> Result<ResourceFile, ApiError> {
+    let content = content.trim_start_matches('\u{feff}');
+    let document = match format {
+        ResourceFormat::Json => Document::Json(
+            serde_json::from_str(content)
+                .map_err(|e| ApiError::BadInput(format!("Invalid JSON: {}", e)))?,
+        ),
+        ResourceFormat::Yaml => Document::Yaml(
+            serde_norway::from_str(content)
+                .map_err(|e| ApiError::BadInput(format!("Invalid YAML: {}", e)))?,
+        ),
+    };
+    let has_keys = match &document {
+        Document::Json(value) => value.is_object(),
+        Document::Yaml(value) => value.is_mapping(),
+    };
+    if !has_keys {
+        return Err(ApiError::BadInput(format!(
+            "The {} file must map keys to strings",
+            format.extension().to_uppercase()
+        )));
+    }
+
+    // Taken from the first indented line
+    let indent = content
+        .lines()
+        .find_map(|line| {
+            let key = line.trim_start();
+            (!key.is_empty() && key.len() < line.len())
+                .then(|| line[..line.len() - key.len()].to_string())
+        })
+        .unwrap_or_else(|| "  ".to_string());
+
+    Ok(ResourceFile {
+        document,
+        indent,
+        line_ending: if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        },
+        trailing_newline: content.ends_with('\n'),
+        document_marker: content.trim_start().starts_with("---"),
+    })
+}
+
+/// Masks the placeholders, nesting and tags in a string value
+pub fn protect(text: &str) -> ProtectedText {
+    let mut pieces = Vec::new();
+    let mut last = 0;
+    for m in markup_pattern().find_iter(text) {
+        pieces.extend(placeholders::split(&text[last..m.start()]));
+        pieces.push(Piece::Placeholder(m.as_str().to_string()));
+        last = m.end();
+    }
+    pieces.extend(placeholders::split(&text[last..]));
+    ProtectedText::from_pieces(pieces)
+}
+
+fn yaml_key(key: &serde_norway::Value) -> String {
+    match key {
+        serde_norway::Value::String(key) => key.clone(),
+        serde_norway::Value::Number(key) => key.to_string(),
+        serde_norway::Value::Bool(key) => key.to_string(),
+        key => serde_norway::to_string(key)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+// Fills in the strings under `value`, returning false when `value` itself is
+// to be left out. An array loses all of its elements rather than renumber them.
+fn fill_json(value: &mut serde_json::Value, path: &mut KeyPath, fill: &mut Fill) -> bool {
+    match value {
+        serde_json::Value::String(text) => match fill(path, text) {
+            Some(filled) => {
+                *text = filled;
+                true
+            }
+            None => false,
+        },
+        serde_json::Value::Array(items) => {
+            let mut keep = true;
+            for (i, item) in items.iter_mut().enumerate() {
+                path.push(i.to_string());
+                keep &= fill_json(item, path, fill);
+                path.pop();
+            }
+            keep
+        }
+        serde_json::Value::Object(map) => {
+            map.retain(|key, item| {
+                path.push(key.clone());
+                let keep = fill_json(item, path, fill);
+                path.pop();
+                keep
+            });
+            true
+        }
+        _ => true,
+    }
+}
+
+// See `fill_json`
+fn fill_yaml(value: &mut serde_norway::Value, path: &mut KeyPath, fill: &mut Fill) -> bool {
+    match value {
+        serde_norway::Value::String(text) => match fill(path, text) {
+            Some(filled) => {
+                *text = filled;
+                true
+            }
+            None => false,
+        },
+        serde_norway::Value::Sequence(items) => {
+            let mut keep = true;
+            for (i, item) in items.iter_mut().enumerate() {
+                path.push(i.to_string());
+                keep &= fill_yaml(item, path, fill);
+                path.pop();
+            }
+            keep
+        }
+        serde_norway::Value::Mapping(map) => {
+            map.retain(|key, item| {
+                path.push(yaml_key(key));
+                let keep = fill_yaml(item, path, fill);
+                path.pop();
+                keep
+            });
+            true
+        }
+        serde_norway::Value::Tagged(tagged) => fill_yaml(&mut tagged.value, path, fill),
+        _ => true,
+    }
+}
+
+impl ResourceFile {
+    fn filled(&self, fill: &mut Fill) -> Document {
+        let mut document = self.document.clone();
+        match &mut document {
+            Document::Json(value) => fill_json(value, &mut Vec::new(), fill),
+            Document::Yaml(value) => fill_yaml(value, &mut Vec::new(), fill),
+        };
+        document
+    }
+
+    /// Every string value with its key path, in file order
+    pub fn strings(&self) -> Vec<(KeyPath, String)> {
+        let mut strings = Vec::new();
+        self.filled(&mut |path, text| {
+            strings.push((path.clone(), text.to_string()));
+            None
+        });
+        strings
+    }
+
+    /// The file with each string value replaced by the one for its key path
+    /// in `values`. Strings without one are left out.
+    pub fn write(&self, values: &HashMap<KeyPath, String>) -> String {
+        let content = match self.filled(&mut |path, _| values.get(path).cloned()) {
+            Document::Json(value) => {
+                let mut output = Vec::new();
+                let formatter =
+                    serde_json::ser::PrettyFormatter::with_indent(self.indent.as_bytes());
+                let mut serializer = serde_json::Serializer::with_formatter(&mut output, formatter);
+                value
+                    .serialize(&mut serializer)
+                    .expect("JSON values always serialize");
+                String::from_utf8(output).expect("serde_json writes UTF-8")
+            }
+            Document::Yaml(value) => {
+                let yaml = serde_norway::to_string(&value).expect("YAML values always serialize");
+                if self.document_marker {
+                    format!("---\n{}", yaml)
+                } else {
+                    yaml
+                }
+            }
+        };
+
+        let mut content = content.trim_end_matches('\n').to_string();
+        if self.trailing_newline {
+            content.push('\n');
+        }
+        if self.line_ending == "\r\n" {
+            content = content.replace('\n', "\r\n");
+        }
+        content
+    }
+}
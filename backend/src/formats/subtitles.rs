@@ -0,0 +1,257 @@
+use crate::placeholders::{Piece, ProtectedText};
+use common::{ApiError, SubtitleFormat};
+use regex::Regex;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// A parsed SRT or WebVTT file. Only the cue text is translated; indices,
+/// timings, cue settings, notes and style blocks are written back as they were.
+pub struct SubtitleFile {
+    lines: Vec<String>,
+    line_ending: &'static str,
+    bom: bool,
+    trailing_newline: bool,
+    /// Text lines of each cue
+    cues: Vec<Range<usize>>,
+    /// Lines translated together, in order: a cue, or each of its dialogue
+    /// lines
+    parts: Vec<CuePart>,
+}
+
+struct CuePart {
+    cue: usize,
+    lines: Range<usize>,
+}
+
+// <i>, </b>, <font color="red">, <c.yellow>, <v Speaker>, <00:01.500> and {\an8}
+fn markup_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"<[^<>\n]*>|\{\\[^{}\n]*\}").expect("valid markup pattern"))
+}
+
+// A speaker's line in a dialogue, "- Hi." or "<i>-Hello.</i>", with its tags
+// and dash
+fn dialogue_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(?:<[^<>\n]*>|\{\\[^{}\n]*\})*[-‐–—]\s*").expect("valid dialogue pattern")
+    })
+}
+
+// The lines of a cue, with one part per speaker when it is a dialogue
+fn cue_parts(cue: usize, lines: &[String], range: Range<usize>, parts: &mut Vec<CuePart>) {
+    let mut start = range.start;
+    for line in range.clone().skip(1) {
+        if dialogue_pattern().is_match(lines[line].trim()) {
+            parts.push(CuePart {
+                cue,
+                lines: start..line,
+            });
+            start = line;
+        }
+    }
+    parts.push(CuePart {
+        cue,
+        lines: start..range.end,
+    });
+}
+
+pub fn detect_format(content: &str) -> SubtitleFormat {
+    if content.trim_start_matches('\u{feff}').starts_with("WEBVTT") {
+        SubtitleFormat::Vtt
+    } else {
+        SubtitleFormat::Srt
+    }
+}
+
+pub fn parse(content: &str, format: SubtitleFormat) -> Result<SubtitleFile, ApiError> {
+    let bom = content.starts_with('\u{feff}');
+    let content = content.trim_start_matches('\u{feff}');
+    if format == SubtitleFormat::Vtt && !content.starts_with("WEBVTT") {
+        return Err(ApiError::BadInput(
+            "WebVTT files must start with \"WEBVTT\"".to_string(),
+        ));
+    }
+
+    let lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let mut cues = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].trim().is_empty() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < lines.len() && !lines[i].trim().is_empty() {
+            i += 1;
+        }
+        // The timing line comes first, or second after a cue number/identifier.
+        // Headers, NOTE, STYLE and REGION blocks have none.
+        let timing = (start..i.min(start + 2)).find(|&line| lines[line].contains("-->"));
+        if let Some(timing) = timing {
+            cues.push(timing + 1..i);
+        }
+    }
+    if cues.is_empty() {
+        return Err(ApiError::BadInput(format!(
+            "No subtitle cues found in the {} file",
+            format.extension().to_uppercase()
+        )));
+    }
+
+    let mut parts = Vec::new();
+    for (cue, range) in cues.iter().enumerate() {
+        cue_parts(cue, &lines, range.clone(), &mut parts);
+    }
+
+    Ok(SubtitleFile {
+        lines,
+        line_ending: if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        },
+        bom,
+        trailing_newline: content.ends_with('\n'),
+        cues,
+        parts,
+    })
+}
+
+// Splits `text` into `count` lines of similar length, breaking only at spaces
+// outside of tags and {\...} override blocks
+fn wrap(text: &str, count: usize) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' | '{' => in_tag = true,
+            '>' | '}' => in_tag = false,
+            _ => {}
+        }
+        if c == ' ' && !in_tag {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    if count <= 1 || words.len() <= 1 {
+        return vec![words.join(" ")];
+    }
+
+    let mut lines: Vec<String> = Vec::with_capacity(count);
+    let mut remaining: usize = words.iter().map(|w| w.chars().count() + 1).sum();
+    let mut line = String::new();
+    for word in words {
+        let lines_left = count - lines.len();
+        let target = remaining / lines_left;
+        let len = line.chars().count();
+        let with_word = len + 1 + word.chars().count();
+        // Break when that gets closer to an even split than adding the word
+        if !line.is_empty()
+            && lines_left > 1
+            && with_word > target
+            && len.abs_diff(target) <= with_word - target
+        {
+            remaining -= len + 1;
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&word);
+    }
+    lines.push(line);
+    lines
+}
+
+impl SubtitleFile {
+    pub fn cue_count(&self) -> usize {
+        self.cues.len()
+    }
+
+    pub fn part_count(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// Cue a part of [`Self::segments`] belongs to
+    pub fn cue_of(&self, part: usize) -> usize {
+        self.parts[part].cue
+    }
+
+    /// Text of every cue with its lines joined and the styling tags masked.
+    /// Each speaker of a dialogue cue gets a segment of their own, with the
+    /// dash masked too.
+    pub fn segments(&self) -> Vec<ProtectedText> {
+        self.parts
+            .iter()
+            .map(|part| {
+                let text = self.lines[part.lines.clone()]
+                    .iter()
+                    .map(|line| line.trim())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let mut pieces = Vec::new();
+                let mut last = 0;
+                if let Some(dash) = dialogue_pattern().find(&text) {
+                    pieces.push(Piece::Placeholder(dash.as_str().to_string()));
+                    last = dash.end();
+                }
+                let body = last;
+                for m in markup_pattern().find_iter(&text[body..]) {
+                    pieces.push(Piece::Text(text[last..body + m.start()].to_string()));
+                    pieces.push(Piece::Placeholder(m.as_str().to_string()));
+                    last = body + m.end();
+                }
+                pieces.push(Piece::Text(text[last..].to_string()));
+                ProtectedText::from_pieces(pieces)
+            })
+            .collect()
+    }
+
+    /// The file with the text of each part of a cue replaced by its
+    /// translation, spread over as many lines as before. Parts without one
+    /// keep their text.
+    pub fn write(&self, translations: &[Option<String>]) -> String {
+        let mut lines: Vec<&str> = Vec::with_capacity(self.lines.len());
+        let mut wrapped: Vec<Vec<String>> = Vec::with_capacity(self.parts.len());
+        for (part, translation) in self.parts.iter().zip(translations) {
+            wrapped.push(match translation {
+                Some(translation) => wrap(translation, part.lines.len()),
+                None => self.lines[part.lines.clone()].to_vec(),
+            });
+        }
+
+        let mut parts = self.parts.iter().zip(&wrapped).peekable();
+        let mut i = 0;
+        while i < self.lines.len() {
+            match parts.peek() {
+                Some((part, text)) if part.lines.start == i => {
+                    lines.extend(text.iter().map(String::as_str));
+                    i = part.lines.end;
+                    parts.next();
+                }
+                _ => {
+                    lines.push(&self.lines[i]);
+                    i += 1;
+                }
+            }
+        }
+
+        let mut output = String::new();
+        if self.bom {
+            output.push('\u{feff}');
+        }
+        output.push_str(&lines.join(self.line_ending));
+        if self.trailing_newline {
+            output.push_str(self.line_ending);
+        }
+        output
+    }
+}
@@ -0,0 +1,473 @@
+use crate::placeholders::{Piece, ProtectedText};
+use common::ApiError;
+use quick_xml::escape::partial_escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::ops::Range;
+
+/// An XLIFF 1.2 or 2.x file. Units whose target is missing or empty can be
+/// pretranslated; the rest of the file is written back byte for byte.
+pub struct XliffFile<'a> {
+    content: &'a str,
+    /// "1.2", "2.0", ...
+    pub version: String,
+    pub source_lang: Option<String>,
+    pub target_lang: Option<String>,
+    /// `<file>` (1.2) or `<xliff>` (2.x) tags that do not name the target language
+    language_tags: Vec<Tag>,
+    units: Vec<XliffUnit>,
+}
+
+// A start tag and its attributes, with values as written
+struct Tag {
+    range: Range<usize>,
+    name: String,
+    attributes: Vec<(String, String)>,
+}
+
+// A trans-unit (1.2) or segment (2.x) waiting for its target
+struct XliffUnit {
+    id: String,
+    /// Contents of `<source>`
+    source: Range<usize>,
+    segment: ProtectedText,
+    target: Target,
+    /// The `<segment>` whose state is set (2.x)
+    segment_tag: Option<Tag>,
+}
+
+enum Target {
+    /// Inserted at `at`, after `</source>`, preceded by `indent`
+    Missing { at: usize, indent: String },
+    /// `<target/>` or `<target></target>`
+    Empty { element: Range<usize>, tag: Tag },
+}
+
+// Inline elements whose content is translated; the content of any other
+// element (<ph>, <bpt>, <it>, ...) is native code kept as is
+const PAIRED_ELEMENTS: &[&str] = &["g", "mrk", "pc"];
+
+// The unit or segment being read
+#[derive(Default)]
+struct UnitBuilder {
+    id: String,
+    depth: usize,
+    skip: bool,
+    translated: bool,
+    source: Option<(Range<usize>, ProtectedText)>,
+    indent: String,
+    target: Option<Target>,
+    segment_tag: Option<Tag>,
+}
+
+// The `<source>` being read
+struct SourceBuilder {
+    start: usize,
+    depth: usize,
+    pieces: Vec<Piece>,
+    // Depth and start of the element whose content is native code
+    native: Option<(usize, usize)>,
+}
+
+// The `<target>` being read
+struct TargetBuilder {
+    tag: Tag,
+    depth: usize,
+    empty: bool,
+}
+
+fn parse_error(position: u64, message: impl std::fmt::Display) -> ApiError {
+    ApiError::BadInput(format!("Invalid XLIFF at byte {}: {}", position, message))
+}
+
+fn tag(e: &BytesStart, range: Range<usize>) -> Result<Tag, ApiError> {
+    let mut attributes = Vec::new();
+    for attribute in e.attributes() {
+        let attribute = attribute.map_err(|e| parse_error(range.start as u64, e))?;
+        attributes.push((
+            String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+            String::from_utf8_lossy(&attribute.value).into_owned(),
+        ));
+    }
+    Ok(Tag {
+        range,
+        name: String::from_utf8_lossy(e.name().as_ref()).into_owned(),
+        attributes,
+    })
+}
+
+fn local_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.local_name().as_ref()).into_owned()
+}
+
+/// Escapes translated text for element content
+pub fn escape(text: &str) -> String {
+    partial_escape(text).into_owned()
+}
+
+impl Tag {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // The start tag with `values` set, written as `<name a="..." b="...">`
+    fn with(&self, values: &[(&str, &str)]) -> String {
+        let mut attributes = self.attributes.clone();
+        for (name, value) in values {
+            let value = escape(value);
+            match attributes.iter_mut().find(|(key, _)| key == name) {
+                Some((_, current)) => *current = value,
+                None => attributes.push((name.to_string(), value)),
+            }
+        }
+        let mut tag = format!("<{}", self.name);
+        for (key, value) in attributes {
+            tag.push_str(&format!(" {}=\"{}\"", key, value.replace('"', "&quot;")));
+        }
+        tag.push('>');
+        tag
+    }
+}
+
+impl SourceBuilder {
+    fn event(&mut self, content: &str, event: &Event, range: Range<usize>) -> Result<(), ApiError> {
+        match event {
+            Event::Start(e) => {
+                self.depth += 1;
+                if self.native.is_none() {
+                    if PAIRED_ELEMENTS.contains(&local_name(e).as_str()) {
+                        self.pieces
+                            .push(Piece::Placeholder(content[range].to_string()));
+                    } else {
+                        self.native = Some((self.depth, range.start));
+                    }
+                }
+            }
+            Event::End(_) => {
+                match self.native {
+                    Some((depth, start)) if depth == self.depth => {
+                        self.pieces
+                            .push(Piece::Placeholder(content[start..range.end].to_string()));
+                        self.native = None;
+                    }
+                    Some(_) => {}
+                    None => self
+                        .pieces
+                        .push(Piece::Placeholder(content[range].to_string())),
+                }
+                self.depth -= 1;
+            }
+            _ if self.native.is_some() => {}
+            Event::Text(e) => {
+                let text = e
+                    .unescape()
+                    .map_err(|e| parse_error(range.start as u64, e))?;
+                self.pieces.push(Piece::Text(text.into_owned()));
+            }
+            Event::CData(e) => self
+                .pieces
+                .push(Piece::Text(String::from_utf8_lossy(e).into_owned())),
+            _ => self
+                .pieces
+                .push(Piece::Placeholder(content[range].to_string())),
+        }
+        Ok(())
+    }
+}
+
+pub fn parse(content: &str) -> Result<XliffFile<'_>, ApiError> {
+    let mut file = XliffFile {
+        content,
+        version: String::new(),
+        source_lang: None,
+        target_lang: None,
+        language_tags: Vec::new(),
+        units: Vec::new(),
+    };
+    let mut reader = Reader::from_str(content);
+    let mut depth = 0usize;
+    // Translate flag of the enclosing 2.x <unit>
+    let mut unit_id = String::new();
+    let mut unit_skip = false;
+    let mut unit: Option<UnitBuilder> = None;
+    let mut source: Option<SourceBuilder> = None;
+    let mut target: Option<TargetBuilder> = None;
+    // The last text between elements when it was only whitespace
+    let mut whitespace: Option<Range<usize>> = None;
+
+    loop {
+        let start = reader.buffer_position() as usize;
+        let event = reader
+            .read_event()
+            .map_err(|e| parse_error(reader.error_position(), e))?;
+        let range = start..reader.buffer_position() as usize;
+        if matches!(event, Event::Eof) {
+            break;
+        }
+        let indent = whitespace
+            .take()
+            .filter(|ws| ws.end == range.start)
+            .map(|ws| content[ws].to_string());
+
+        if let Some(builder) = &mut source {
+            if matches!(event, Event::End(_)) && builder.depth == 0 {
+                let builder = source.take().expect("source being read");
+                if let Some(unit) = &mut unit {
+                    let segment = ProtectedText::from_pieces(builder.pieces).escaping(escape);
+                    unit.source = Some((builder.start..range.start, segment));
+                    unit.target.get_or_insert(Target::Missing {
+                        at: range.end,
+                        indent: unit.indent.clone(),
+                    });
+                }
+            } else {
+                builder.event(content, &event, range)?;
+            }
+            continue;
+        }
+        // <source> and <target> are read to their end without counting depth
+        if let Some(builder) = &mut target {
+            match &event {
+                Event::Start(_) => builder.depth += 1,
+                Event::End(_) if builder.depth == 0 => {
+                    let builder = target.take().expect("target being read");
+                    if let Some(unit) = &mut unit {
+                        if builder.empty {
+                            unit.target = Some(Target::Empty {
+                                element: builder.tag.range.start..range.end,
+                                tag: builder.tag,
+                            });
+                        } else {
+                            unit.translated = true;
+                        }
+                    }
+                    continue;
+                }
+                Event::End(_) => builder.depth -= 1,
+                Event::Text(e) if e.iter().all(u8::is_ascii_whitespace) => continue,
+                _ => {}
+            }
+            builder.empty = false;
+            continue;
+        }
+
+        match &event {
+            Event::Start(e) | Event::Empty(e) => {
+                let name = local_name(e);
+                let is_empty = matches!(event, Event::Empty(_));
+                let child_of_unit = unit.as_ref().is_some_and(|u| u.depth == depth);
+                match name.as_str() {
+                    "xliff" if depth == 0 => {
+                        let tag = tag(e, range.clone())?;
+                        file.version = tag.get("version").unwrap_or_default().to_string();
+                        if !file.version.starts_with('1') {
+                            file.source_lang = tag.get("srcLang").map(str::to_string);
+                            file.target_lang = tag.get("trgLang").map(str::to_string);
+                            if file.target_lang.is_none() {
+                                file.language_tags.push(tag);
+                            }
+                        }
+                    }
+                    _ if depth == 0 => {
+                        return Err(ApiError::BadInput(
+                            "Not an XLIFF file: the root element must be <xliff>".to_string(),
+                        ))
+                    }
+                    "file" if file.version.starts_with('1') => {
+                        let tag = tag(e, range.clone())?;
+                        if file.source_lang.is_none() {
+                            file.source_lang = tag.get("source-language").map(str::to_string);
+                        }
+                        match tag.get("target-language") {
+                            Some(lang) => {
+                                file.target_lang.get_or_insert_with(|| lang.to_string());
+                            }
+                            None => file.language_tags.push(tag),
+                        }
+                    }
+                    "trans-unit" if file.version.starts_with('1') && !is_empty => {
+                        let tag = tag(e, range.clone())?;
+                        unit = Some(UnitBuilder {
+                            id: tag.get("id").unwrap_or_default().to_string(),
+                            depth: depth + 1,
+                            skip: tag.get("translate") == Some("no"),
+                            ..Default::default()
+                        });
+                    }
+                    "unit" if !is_empty => {
+                        let tag = tag(e, range.clone())?;
+                        unit_id = tag.get("id").unwrap_or_default().to_string();
+                        unit_skip = tag.get("translate") == Some("no");
+                    }
+                    "segment" if !file.version.starts_with('1') && !is_empty => {
+                        let tag = tag(e, range.clone())?;
+                        let id = match tag.get("id") {
+                            Some(segment) => format!("{}/{}", unit_id, segment),
+                            None => unit_id.clone(),
+                        };
+                        unit = Some(UnitBuilder {
+                            id,
+                            depth: depth + 1,
+                            skip: unit_skip,
+                            segment_tag: Some(tag),
+                            ..Default::default()
+                        });
+                    }
+                    "source" if child_of_unit && !is_empty => {
+                        if let Some(unit) = &mut unit {
+                            unit.indent = indent.unwrap_or_default();
+                        }
+                        source = Some(SourceBuilder {
+                            start: range.end,
+                            depth: 0,
+                            pieces: Vec::new(),
+                            native: None,
+                        });
+                        continue;
+                    }
+                    // Segmented 1.2 sources would need <mrk> segments in the target
+                    "seg-source" if child_of_unit => {
+                        if let Some(unit) = &mut unit {
+                            unit.skip = true;
+                        }
+                    }
+                    "target" if child_of_unit => {
+                        let tag = tag(e, range.clone())?;
+                        if is_empty {
+                            if let Some(unit) = &mut unit {
+                                unit.target = Some(Target::Empty {
+                                    element: range.clone(),
+                                    tag,
+                                });
+                            }
+                        } else {
+                            target = Some(TargetBuilder {
+                                tag,
+                                depth: 0,
+                                empty: true,
+                            });
+                        }
+                        continue;
+                    }
+                    _ => {}
+                }
+                if !is_empty {
+                    depth += 1;
+                }
+            }
+            Event::End(_) => {
+                if unit.as_ref().is_some_and(|u| u.depth == depth) {
+                    let builder = unit.take().expect("unit being read");
+                    if let (false, false, Some((source, segment)), Some(target)) = (
+                        builder.skip,
+                        builder.translated,
+                        builder.source,
+                        builder.target,
+                    ) {
+                        file.units.push(XliffUnit {
+                            id: builder.id,
+                            source,
+                            segment,
+                            target,
+                            segment_tag: builder.segment_tag,
+                        });
+                    }
+                }
+                depth = depth.saturating_sub(1);
+            }
+            Event::Text(e) if e.iter().all(u8::is_ascii_whitespace) => whitespace = Some(range),
+            _ => {}
+        }
+    }
+
+    if file.version.is_empty() {
+        return Err(ApiError::BadInput(
+            "Not an XLIFF file: <xliff version=\"...\"> is missing".to_string(),
+        ));
+    }
+    Ok(file)
+}
+
+impl XliffFile<'_> {
+    /// Source text of each unit waiting for a target, with its inline
+    /// elements masked
+    pub fn segments(&self) -> Vec<ProtectedText> {
+        self.units.iter().map(|unit| unit.segment.clone()).collect()
+    }
+
+    /// Contents of the unit's `<source>`, as written
+    pub fn source(&self, unit: usize) -> &str {
+        &self.content[self.units[unit].source.clone()]
+    }
+
+    /// Id of the trans-unit, or "unit/segment"
+    pub fn unit_id(&self, unit: usize) -> &str {
+        &self.units[unit].id
+    }
+
+    /// The file with a target holding each translation, given as XML
+    /// content. Machine translations are marked for review: 1.2 targets get
+    /// `state="needs-review-translation"`, 2.x segments `state="translated"`.
+    /// `language` fills in a missing target language.
+    pub fn write(&self, translations: &[Option<String>], language: &str) -> String {
+        let is_v1 = self.version.starts_with('1');
+        let target_state: &[(&str, &str)] = if is_v1 {
+            &[
+                ("state", "needs-review-translation"),
+                ("state-qualifier", "mt-suggestion"),
+            ]
+        } else {
+            &[]
+        };
+
+        let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+        for tag in &self.language_tags {
+            let attribute = if is_v1 { "target-language" } else { "trgLang" };
+            edits.push((tag.range.clone(), tag.with(&[(attribute, language)])));
+        }
+        for (unit, translation) in self.units.iter().zip(translations) {
+            let Some(translation) = translation else {
+                continue;
+            };
+            if let Some(tag) = &unit.segment_tag {
+                edits.push((tag.range.clone(), tag.with(&[("state", "translated")])));
+            }
+            match &unit.target {
+                Target::Missing { at, indent } => {
+                    let tag = Tag {
+                        range: 0..0,
+                        name: "target".to_string(),
+                        attributes: Vec::new(),
+                    };
+                    edits.push((
+                        *at..*at,
+                        format!(
+                            "{}{}{}</target>",
+                            indent,
+                            tag.with(target_state),
+                            translation
+                        ),
+                    ));
+                }
+                Target::Empty { element, tag } => edits.push((
+                    element.clone(),
+                    format!("{}{}</{}>", tag.with(target_state), translation, tag.name),
+                )),
+            }
+        }
+        edits.sort_by_key(|(range, _)| range.start);
+
+        let mut output = String::with_capacity(self.content.len());
+        let mut pos = 0;
+        for (range, text) in edits {
+            output.push_str(&self.content[pos..range.start]);
+            output.push_str(&text);
+            pos = range.end;
+        }
+        output.push_str(&self.content[pos..]);
+        output
+    }
+}
@@ -0,0 +1,94 @@
+use crate::auth::AuthUser;
+use crate::error::AppError;
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use common::{ApiError, CreateGlossaryRequest, GlossaryInfo, GlossaryList};
+
+// Ids end up in upstream URL paths, so only accept what DeepL issues (UUIDs)
+fn check_glossary_id(glossary_id: &str) -> Result<(), AppError> {
+    let valid = !glossary_id.is_empty()
+        && glossary_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(ApiError::BadInput(format!("Invalid glossary id '{}'", glossary_id)).into())
+    }
+}
+
+// Glossaries are shared by everyone on the upstream account, so only admins
+// may create or delete them
+pub async fn create_glossary(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateGlossaryRequest>,
+) -> Result<(StatusCode, Json<GlossaryInfo>), AppError> {
+    user.require_admin()?;
+    if req.name.trim().is_empty() {
+        return Err(ApiError::BadInput("Glossary name must not be empty".to_string()).into());
+    }
+    if req.source_lang.trim().is_empty() || req.target_lang.trim().is_empty() {
+        return Err(
+            ApiError::BadInput("source_lang and target_lang are required".to_string()).into(),
+        );
+    }
+    if req.entries.trim().is_empty() {
+        return Err(ApiError::BadInput("Glossary has no entries".to_string()).into());
+    }
+
+    let glossary = state.provider.create_glossary(&req).await?;
+    Ok((StatusCode::CREATED, Json(glossary)))
+}
+
+pub async fn list_glossaries(
+    State(state): State<AppState>,
+) -> Result<Json<GlossaryList>, AppError> {
+    Ok(Json(GlossaryList {
+        glossaries: state.provider.list_glossaries().await?,
+    }))
+}
+
+pub async fn glossary(
+    State(state): State<AppState>,
+    Path(glossary_id): Path<String>,
+) -> Result<Json<GlossaryInfo>, AppError> {
+    check_glossary_id(&glossary_id)?;
+    Ok(Json(state.provider.glossary(&glossary_id).await?))
+}
+
+/// Entries as a TSV download
+pub async fn glossary_entries(
+    State(state): State<AppState>,
+    Path(glossary_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    check_glossary_id(&glossary_id)?;
+    let entries = state.provider.glossary_entries(&glossary_id).await?;
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                "text/tab-separated-values; charset=utf-8".to_string(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.tsv\"", glossary_id),
+            ),
+        ],
+        entries,
+    ))
+}
+
+pub async fn delete_glossary(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(glossary_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    user.require_admin()?;
+    check_glossary_id(&glossary_id)?;
+    state.provider.delete_glossary(&glossary_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
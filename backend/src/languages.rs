@@ -0,0 +1,161 @@
+use crate::error::AppError;
+use crate::provider::{ProviderError, TranslateOptions, TranslationProvider};
+use common::{ApiError, Language, LanguagesResponse, FORMALITIES};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Caches the provider's language lists and validates requested languages
+/// against them before anything is sent upstream.
+pub struct LanguageCatalog {
+    provider: Arc<dyn TranslationProvider>,
+    ttl: Duration,
+    // Held across the upstream call so concurrent requests share one fetch
+    cached: tokio::sync::Mutex<Cached>,
+}
+
+#[derive(Default)]
+struct Cached {
+    languages: Option<(Instant, Arc<LanguagesResponse>)>,
+    /// When the last fetch failed, so an outage costs one upstream call per
+    /// `RETRY_AFTER_FAILURE` rather than one per request
+    failed_at: Option<Instant>,
+}
+
+const RETRY_AFTER_FAILURE: Duration = Duration::from_secs(60);
+
+fn find<'a>(languages: &'a [Language], code: &str) -> Option<&'a Language> {
+    languages.iter().find(|l| l.code.eq_ignore_ascii_case(code))
+}
+
+impl LanguageCatalog {
+    pub fn new(provider: Arc<dyn TranslationProvider>, ttl: Duration) -> Self {
+        Self {
+            provider,
+            ttl,
+            cached: tokio::sync::Mutex::new(Cached::default()),
+        }
+    }
+
+    /// The cached lists, fetched again once they are older than the TTL. After
+    /// a failed fetch, expired lists are still served and the upstream is not
+    /// asked again for `RETRY_AFTER_FAILURE`.
+    pub async fn get(&self) -> Result<Arc<LanguagesResponse>, ProviderError> {
+        let mut cached = self.cached.lock().await;
+        let retry_in = cached
+            .failed_at
+            .map(|failed_at| RETRY_AFTER_FAILURE.saturating_sub(failed_at.elapsed()))
+            .filter(|retry_in| !retry_in.is_zero());
+        if let Some((fetched_at, languages)) = &cached.languages {
+            if fetched_at.elapsed() < self.ttl || retry_in.is_some() {
+                return Ok(languages.clone());
+            }
+        }
+        if let Some(retry_in) = retry_in {
+            // The upstream was not called, as with an open circuit
+            return Err(ProviderError::CircuitOpen { retry_in });
+        }
+
+        match self.provider.languages().await {
+            Ok(languages) => {
+                let languages = Arc::new(languages);
+                *cached = Cached {
+                    languages: Some((Instant::now(), languages.clone())),
+                    failed_at: None,
+                };
+                Ok(languages)
+            }
+            Err(ProviderError::Unsupported(feature)) => Err(ProviderError::Unsupported(feature)),
+            Err(e) => {
+                cached.failed_at = Some(Instant::now());
+                match &cached.languages {
+                    Some((_, languages)) => {
+                        warn!(
+                            "Serving expired language lists, could not fetch them: {}",
+                            e
+                        );
+                        Ok(languages.clone())
+                    }
+                    None => Err(e),
+                }
+            }
+        }
+    }
+
+    /// Looks up a target language. A bare code like "EN" also matches when
+    /// only regional variants ("EN-GB", "EN-US") are listed, since DeepL still
+    /// accepts it.
+    pub fn find_target<'a>(languages: &'a LanguagesResponse, code: &str) -> Option<&'a Language> {
+        find(&languages.target, code).or_else(|| {
+            languages.target.iter().find(|l| {
+                l.code
+                    .split_once('-')
+                    .is_some_and(|(base, _)| base.eq_ignore_ascii_case(code))
+            })
+        })
+    }
+
+    /// Rejects unknown language codes, formality levels the target language
+    /// does not support and glossaries without a source language. When the
+    /// list cannot be fetched the request is let through and the upstream gets
+    /// to decide.
+    pub async fn validate(&self, options: &TranslateOptions) -> Result<(), AppError> {
+        if options.glossary_id.is_some() && options.source_lang.is_none() {
+            return Err(ApiError::BadInput(
+                "source_lang is required when using a glossary".to_string(),
+            )
+            .into());
+        }
+        if let Some(formality) = options.formality.as_deref() {
+            if !FORMALITIES.contains(&formality) {
+                return Err(ApiError::BadInput(format!(
+                    "Unsupported formality '{}', expected one of: {}",
+                    formality,
+                    FORMALITIES.join(", ")
+                ))
+                .into());
+            }
+        }
+
+        let languages = match self.get().await {
+            Ok(languages) => languages,
+            Err(ProviderError::Unsupported(_)) => return Ok(()),
+            Err(e) => {
+                warn!(
+                    "Skipping language validation, could not fetch languages: {}",
+                    e
+                );
+                return Ok(());
+            }
+        };
+
+        if let Some(source_lang) = options.source_lang.as_deref() {
+            if find(&languages.source, source_lang).is_none() {
+                return Err(ApiError::BadInput(format!(
+                    "Unsupported source language '{}'. See /languages for the supported codes",
+                    source_lang
+                ))
+                .into());
+            }
+        }
+        let target_lang = &options.target_lang;
+        let Some(target) = Self::find_target(&languages, target_lang) else {
+            return Err(ApiError::BadInput(format!(
+                "Unsupported target language '{}'. See /languages for the supported codes",
+                target_lang
+            ))
+            .into());
+        };
+        // DeepL fails hard on "more"/"less" for such targets but ignores "prefer_*"
+        if matches!(options.formality.as_deref(), Some("more" | "less"))
+            && !target.supports_formality
+        {
+            return Err(ApiError::BadInput(format!(
+                "Target language '{}' does not support formality. Use prefer_more or prefer_less instead",
+                target_lang
+            ))
+            .into());
+        }
+        Ok(())
+    }
+}
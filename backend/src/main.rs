@@ -1,206 +1,524 @@
 use axum::{
     extract::State,
-    http::StatusCode,
+    http::{HeaderValue, StatusCode},
+    middleware,
     response::Json,
-    routing::{get_service, post},
-    Router,
+    routing::{delete, get, get_service, post},
+    Extension, Router,
 };
 use tower_http::services::fs::ServeDir;
-use common::{ApiResponse, ImproveRequest, TranslateRequest};
-use serde::{Deserialize, Serialize};
+use common::{
+    word_diff, ApiError, ApiResponse, AuthInfo, BatchTranslateRequest, BatchTranslateResponse,
+    BatchTranslateResult, ImproveRequest, LanguagesResponse, ProviderUsage, TextFormat,
+    TranslateRequest, UsageReport, TONES, WRITING_STYLES,
+};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tower_http::cors::CorsLayer;
 use tracing::{info, warn};
 
+mod account_usage;
+mod auth;
+mod batch;
+mod cache;
+mod error;
+mod files;
+mod formats;
+mod glossaries;
+mod languages;
+mod markdown;
+mod markup;
+mod placeholders;
+mod provider;
+mod terminology;
+mod usage;
+
+use account_usage::AccountUsageMonitor;
+use auth::{AuthUser, Authenticator};
+use cache::{CacheConfig, CacheKey, CacheOperation, CachedResult, TranslationCache};
+use error::AppError;
+use languages::LanguageCatalog;
+use placeholders::ProtectedText;
+use terminology::{MaskedText, Terminology};
+use usage::{billed_chars, Quota, UsageTracker};
+use provider::{
+    CircuitBreaker, CircuitState, DeepLProvider, ImproveOptions, LibreTranslateProvider,
+    ResilientProvider, RetryPolicy, TranslateOptions, TranslationProvider,
+};
+
 #[derive(Clone)]
 struct AppState {
-    client: reqwest::Client,
-    deepl_api_key: String,
+    provider: Arc<dyn TranslationProvider>,
+    circuit_breaker: Arc<CircuitBreaker>,
+    cache: Arc<TranslationCache>,
+    authenticator: Arc<Authenticator>,
+    usage: Arc<UsageTracker>,
+    account_usage: Arc<AccountUsageMonitor>,
+    languages: Arc<LanguageCatalog>,
+    terminology: Arc<Terminology>,
 }
 
-#[derive(Deserialize)]
-struct DeepLTranslateResponse {
-    translations: Vec<DeepLTranslation>,
-}
+// Response carrying an X-Cache: HIT/MISS header
+type CachedJson = ([(&'static str, &'static str); 1], Json<ApiResponse>);
 
-#[derive(Deserialize)]
-struct DeepLTranslation {
-    text: String,
+fn cached_response(cache_status: &'static str, value: CachedResult) -> CachedJson {
+    (
+        [("x-cache", cache_status)],
+        Json(ApiResponse {
+            result: value.result,
+            success: true,
+            error: None,
+            detected_source_language: value.detected_source_language,
+            diff: None,
+            applied_terms: Vec::new(),
+        }),
+    )
 }
 
-#[derive(Serialize)]
-struct DeepLTranslateRequest {
-    text: Vec<String>,
-    target_lang: String,
+async fn translate_text(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<TranslateRequest>,
+) -> Result<CachedJson, AppError> {
+    if req.text.trim().is_empty() {
+        return Err(ApiError::BadInput("Text must not be empty".to_string()).into());
+    }
+
+    let options = TranslateOptions {
+        source_lang: req.source_lang,
+        target_lang: req.target_lang.unwrap_or_else(|| "EN".to_string()),
+        formality: req.formality.filter(|f| !f.is_empty()),
+        glossary_id: req.glossary_id.filter(|g| !g.is_empty()),
+        tags: req.tags,
+        context: None,
+    };
+    markup::validate_tag_options(&options.tags)?;
+    state.languages.validate(&options).await?;
+
+    if req.format == TextFormat::Markdown {
+        if options.tags.tag_handling.is_some() {
+            return Err(ApiError::BadInput(
+                "tag_handling cannot be combined with format markdown".to_string(),
+            )
+            .into());
+        }
+        return translate_markdown(&state, &user, &req.text, &options, req.protect_placeholders).await;
+    }
+
+    // Placeholders and terms are masked before the cache so the same masked text
+    // shares a cache entry, and changed target terms apply to cached results too
+    let protected = protect(&req.text, req.protect_placeholders);
+    let masked = state.terminology.mask(&protected.text, &options);
+    let key = CacheKey::new(CacheOperation::Translate, &masked.text, &options);
+    if let Some(cached) = state.cache.get(&key) {
+        return translated_response(&protected, &masked, "HIT", cached);
+    }
+
+    let billed = billed_chars([&masked.text]);
+    let translations = state
+        .usage
+        .metered(&user, billed, state.provider.translate(vec![masked.text.clone()], &options))
+        .await?;
+    let translation = translations
+        .into_iter()
+        .next()
+        .ok_or_else(|| ApiError::ParseFailure("No translation returned".to_string()))?;
+    if let Some(mode) = options.tags.tag_handling {
+        markup::check_tags(&masked.text, &translation.text, mode)?;
+    }
+    protected.check(&translation.text)?;
+
+    let value = CachedResult {
+        result: translation.text,
+        detected_source_language: translation.detected_source_language,
+    };
+    state.cache.insert(key, value.clone());
+    translated_response(&protected, &masked, "MISS", value)
 }
 
-#[derive(Serialize)]
-struct DeepLImproveRequest {
-    text: Vec<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    target_lang: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    writing_style: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    tone: Option<String>,
+/// `/translate` with `format: markdown`. Segments are cached one by one, so
+/// editing a paragraph only retranslates that paragraph.
+async fn translate_markdown(
+    state: &AppState,
+    user: &AuthUser,
+    text: &str,
+    options: &TranslateOptions,
+    protect_placeholders: bool,
+) -> Result<CachedJson, AppError> {
+    let document = markdown::parse(text, protect_placeholders);
+    let translated = files::translate_segments(state, user, &document.segments, options).await?;
+    let texts = translated.texts.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+    let value = CachedResult {
+        result: document.reassemble(&texts),
+        detected_source_language: translated.detected_source_language,
+    };
+    let (headers, Json(mut body)) = cached_response(if translated.cached { "HIT" } else { "MISS" }, value);
+    body.applied_terms = translated.applied_terms;
+    Ok((headers, Json(body)))
 }
 
-#[derive(Deserialize)]
-struct DeepLImproveResponse {
-    improvements: Vec<DeepLImprovement>,
+fn protect(text: &str, protect_placeholders: bool) -> ProtectedText {
+    if protect_placeholders {
+        placeholders::protect(text)
+    } else {
+        ProtectedText::plain(text)
+    }
 }
 
-#[derive(Deserialize)]
-struct DeepLImprovement {
-    detected_source_language: String,
-    text: String,
+/// Like `cached_response`, with the local terminology and the placeholders put
+/// back into the result
+fn translated_response(
+    protected: &ProtectedText,
+    masked: &MaskedText,
+    cache_status: &'static str,
+    mut value: CachedResult,
+) -> Result<CachedJson, AppError> {
+    let (result, applied_terms) = masked.restore(&value.result);
+    value.result = protected.restore(&result)?;
+    let (headers, Json(mut body)) = cached_response(cache_status, value);
+    body.applied_terms = applied_terms;
+    Ok((headers, Json(body)))
 }
 
-async fn translate_text(
+async fn translate_batch(
     State(state): State<AppState>,
-    Json(req): Json<TranslateRequest>,
-) -> Result<Json<ApiResponse>, StatusCode> {
-    let target_lang = req.target_lang.unwrap_or_else(|| "EN".to_string());
-    
-    let deepl_req = DeepLTranslateRequest {
-        text: vec![req.text],
-        target_lang,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<BatchTranslateRequest>,
+) -> Result<Json<BatchTranslateResponse>, AppError> {
+    let options = TranslateOptions {
+        source_lang: req.source_lang,
+        target_lang: req.target_lang.unwrap_or_else(|| "EN".to_string()),
+        formality: req.formality.filter(|f| !f.is_empty()),
+        glossary_id: req.glossary_id.filter(|g| !g.is_empty()),
+        tags: req.tags,
+        context: None,
     };
+    markup::validate_tag_options(&options.tags)?;
+    state.languages.validate(&options).await?;
 
-    let response = state
-        .client
-        .post("https://api-free.deepl.com/v2/translate")
-        .header("Authorization", format!("DeepL-Auth-Key {}", state.deepl_api_key))
-        .json(&deepl_req)
-        .send()
-        .await;
-
-    match response {
-        Ok(resp) => {
-            if resp.status().is_success() {
-                match resp.json::<DeepLTranslateResponse>().await {
-                    Ok(deepl_resp) => {
-                        if let Some(translation) = deepl_resp.translations.first() {
-                            Ok(Json(ApiResponse {
-                                result: translation.text.clone(),
-                                success: true,
-                                error: None,
-                            }))
-                        } else {
-                            Ok(Json(ApiResponse {
-                                result: String::new(),
-                                success: false,
-                                error: Some("No translation returned".to_string()),
-                            }))
-                        }
-                    }
-                    Err(e) => {
-                        warn!("Failed to parse DeepL response: {}", e);
-                        Ok(Json(ApiResponse {
-                            result: String::new(),
-                            success: false,
-                            error: Some("Failed to parse response".to_string()),
-                        }))
-                    }
+    let (ids, masked): (Vec<String>, Vec<(ProtectedText, MaskedText)>) = req
+        .items
+        .into_iter()
+        .map(|item| {
+            let protected = protect(&item.text, req.protect_placeholders);
+            let masked = state.terminology.mask(&protected.text, &options);
+            (item.id, (protected, masked))
+        })
+        .unzip();
+    let texts: Vec<String> = masked.iter().map(|(_, m)| m.text.clone()).collect();
+
+    let billed = billed_chars(&texts);
+    let translations = state
+        .usage
+        .metered(
+            &user,
+            billed,
+            batch::translate_batched(state.provider.as_ref(), texts, &options),
+        )
+        .await?;
+
+    Ok(Json(BatchTranslateResponse {
+        results: ids
+            .into_iter()
+            .zip(masked)
+            .zip(translations)
+            .map(|((id, (protected, masked)), translation)| {
+                // One item with broken markup or placeholders should not fail the whole batch
+                let restored = match options.tags.tag_handling {
+                    Some(mode) => markup::check_tags(&masked.text, &translation.text, mode),
+                    None => Ok(()),
                 }
-            } else {
-                let status = resp.status();
-                let error_text = resp.text().await.unwrap_or_default();
-                warn!("DeepL API error: {} - {}", status, error_text);
-                Ok(Json(ApiResponse {
-                    result: String::new(),
-                    success: false,
-                    error: Some(format!("API error: {}", status)),
-                }))
-            }
-        }
-        Err(e) => {
-            warn!("Request failed: {}", e);
-            Ok(Json(ApiResponse {
-                result: String::new(),
-                success: false,
-                error: Some("Network error".to_string()),
-            }))
-        }
-    }
+                .and_then(|()| {
+                    let (result, applied_terms) = masked.restore(&translation.text);
+                    Ok((protected.restore(&result)?, applied_terms))
+                });
+                match restored {
+                    Ok((result, applied_terms)) => BatchTranslateResult {
+                        id,
+                        result,
+                        detected_source_language: translation.detected_source_language,
+                        applied_terms,
+                        error: None,
+                    },
+                    Err(error) => BatchTranslateResult {
+                        id,
+                        result: String::new(),
+                        detected_source_language: translation.detected_source_language,
+                        applied_terms: Vec::new(),
+                        error: Some(error),
+                    },
+                }
+            })
+            .collect(),
+        success: true,
+        error: None,
+    }))
 }
 
 async fn improve_text(
     State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
     Json(req): Json<ImproveRequest>,
-) -> Result<Json<ApiResponse>, StatusCode> {
-    let deepl_req = DeepLImproveRequest {
-        text: vec![req.text],
+) -> Result<CachedJson, AppError> {
+    if req.text.trim().is_empty() {
+        return Err(ApiError::BadInput("Text must not be empty".to_string()).into());
+    }
+
+    let options = ImproveOptions {
         target_lang: req.target_lang,
-        writing_style: req.writing_style,
-        tone: req.tone,
+        writing_style: req.writing_style.filter(|s| !s.is_empty()),
+        tone: req.tone.filter(|t| !t.is_empty()),
     };
+    validate_improve_options(&options)?;
 
-    let response = state
-        .client
-        .post("https://api-free.deepl.com/v2/write/rephrase")
-        .header("Authorization", format!("DeepL-Auth-Key {}", state.deepl_api_key))
-        .json(&deepl_req)
-        .send()
-        .await;
-
-    match response {
-        Ok(resp) => {
-            if resp.status().is_success() {
-                match resp.json::<DeepLImproveResponse>().await {
-                    Ok(deepl_resp) => {
-                        if let Some(improvement) = deepl_resp.improvements.first() {
-                            Ok(Json(ApiResponse {
-                                result: improvement.text.clone(),
-                                success: true,
-                                error: None,
-                            }))
-                        } else {
-                            Ok(Json(ApiResponse {
-                                result: String::new(),
-                                success: false,
-                                error: Some("No improvement returned".to_string()),
-                            }))
-                        }
-                    }
-                    Err(e) => {
-                        warn!("Failed to parse DeepL response: {}", e);
-                        Ok(Json(ApiResponse {
-                            result: String::new(),
-                            success: false,
-                            error: Some("Failed to parse response".to_string()),
-                        }))
-                    }
-                }
-            } else {
-                let status = resp.status();
-                let error_text = resp.text().await.unwrap_or_default();
-                warn!("DeepL API error: {} - {}", status, error_text);
-                Ok(Json(ApiResponse {
-                    result: String::new(),
-                    success: false,
-                    error: Some(format!("API error: {}", status)),
-                }))
-            }
+    let key = CacheKey::new(CacheOperation::Improve, &req.text, &options);
+    if let Some(cached) = state.cache.get(&key) {
+        return Ok(improved_response(&req.text, "HIT", cached));
+    }
+
+    let billed = billed_chars([&req.text]);
+    let improvements = state
+        .usage
+        .metered(&user, billed, state.provider.improve(vec![req.text.clone()], &options))
+        .await?;
+    let improvement = improvements
+        .into_iter()
+        .next()
+        .ok_or_else(|| ApiError::ParseFailure("No improvement returned".to_string()))?;
+
+    let value = CachedResult {
+        result: improvement.text,
+        detected_source_language: improvement.detected_source_language,
+    };
+    state.cache.insert(key, value.clone());
+    Ok(improved_response(&req.text, "MISS", value))
+}
+
+/// Like `cached_response`, plus the diff from `original`. Computed per request
+/// since cache keys ignore surrounding whitespace.
+fn improved_response(original: &str, cache_status: &'static str, value: CachedResult) -> CachedJson {
+    let diff = word_diff(original, &value.result);
+    let (headers, Json(mut body)) = cached_response(cache_status, value);
+    body.diff = Some(diff);
+    (headers, Json(body))
+}
+
+/// DeepL Write rejects requests that set both a writing style and a tone
+fn validate_improve_options(options: &ImproveOptions) -> Result<(), ApiError> {
+    if options.writing_style.is_some() && options.tone.is_some() {
+        return Err(ApiError::BadInput(
+            "writing_style and tone cannot be set at the same time".to_string(),
+        ));
+    }
+    if let Some(style) = options.writing_style.as_deref() {
+        if !WRITING_STYLES.contains(&style) {
+            return Err(ApiError::BadInput(format!(
+                "Unsupported writing_style '{}', expected one of: {}",
+                style,
+                WRITING_STYLES.join(", ")
+            )));
         }
-        Err(e) => {
-            warn!("Request failed: {}", e);
-            Ok(Json(ApiResponse {
-                result: String::new(),
-                success: false,
-                error: Some("Network error".to_string()),
-            }))
+    }
+    if let Some(tone) = options.tone.as_deref() {
+        if !TONES.contains(&tone) {
+            return Err(ApiError::BadInput(format!(
+                "Unsupported tone '{}', expected one of: {}",
+                tone,
+                TONES.join(", ")
+            )));
         }
     }
+    Ok(())
+}
+
+async fn cache_stats(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    user.require_admin()?;
+    Ok(Json(serde_json::json!(state.cache.stats())))
+}
+
+async fn purge_cache(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    user.require_admin()?;
+    let purged = state.cache.purge();
+    info!("Purged {} cache entries", purged);
+    if let Err(e) = state.cache.save().await {
+        warn!("Failed to persist translation cache: {}", e);
+    }
+    Ok(Json(serde_json::json!({ "purged": purged })))
+}
+
+async fn auth_me(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> Json<AuthInfo> {
+    Json(AuthInfo {
+        user: user.name,
+        admin: user.admin,
+        auth_required: state.authenticator.is_required(),
+    })
 }
 
-async fn health_check() -> Result<Json<serde_json::Value>, StatusCode> {
+async fn user_usage(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> Json<UsageReport> {
+    Json(state.usage.report(&user))
+}
+
+async fn list_languages(
+    State(state): State<AppState>,
+) -> Result<Json<LanguagesResponse>, AppError> {
+    let languages = state.languages.get().await?;
+    Ok(Json(languages.as_ref().clone()))
+}
+
+async fn provider_usage(State(state): State<AppState>) -> Result<Json<ProviderUsage>, AppError> {
+    Ok(Json(state.account_usage.get().await?))
+}
+
+async fn health_check(State(state): State<AppState>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let circuit = state.circuit_breaker.snapshot();
+    // Still 200 when the upstream is down, so orchestrators don't restart the proxy
+    let status = match circuit.state {
+        CircuitState::Closed => "healthy",
+        _ => "degraded",
+    };
+
+    // Only what the background monitor last saw, so /health never waits on the
+    // upstream. The share used, as the route needs no token.
+    let account_usage = state.account_usage.latest().map(|usage| {
+        serde_json::json!({
+            "provider": usage.provider,
+            "percent_used": (usage.fraction_used() * 1000.0).round() / 10.0,
+        })
+    });
+
     Ok(Json(serde_json::json!({
-        "status": "healthy",
+        "status": status,
         "service": "deepl-proxy",
-        "version": env!("CARGO_PKG_VERSION")
+        "version": env!("CARGO_PKG_VERSION"),
+        "upstream": {
+            "provider": state.provider.name(),
+            "circuit_breaker": circuit,
+            "account_usage": account_usage
+        }
     })))
 }
 
+// Reads an optional numeric setting, falling back to `default` when unset or invalid
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    match std::env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            warn!("Ignoring invalid value for {}: {}", name, value);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+// Like `env_or`, for settings that are off when unset
+fn env_opt<T: std::str::FromStr>(name: &str) -> Option<T> {
+    let value = std::env::var(name).ok()?;
+    value
+        .parse()
+        .map_err(|_| warn!("Ignoring invalid value for {}: {}", name, value))
+        .ok()
+}
+
+// Picks the upstream engine from TRANSLATION_PROVIDER (default: deepl)
+fn build_provider(client: reqwest::Client) -> anyhow::Result<Arc<dyn TranslationProvider>> {
+    let provider_name =
+        std::env::var("TRANSLATION_PROVIDER").unwrap_or_else(|_| "deepl".to_string());
+
+    match provider_name.to_lowercase().as_str() {
+        "deepl" => {
+            let deepl_api_key = std::env::var("DEEPL_API_KEY")
+                .unwrap_or_else(|_| "your-deepl-api-key-here".to_string());
+
+            if deepl_api_key == "your-deepl-api-key-here" {
+                warn!("Using placeholder API key. Set DEEPL_API_KEY environment variable.");
+            }
+
+            // DEEPL_API_URL overrides the endpoint derived from the key, e.g. for a local mock
+            let deepl_base_url = std::env::var("DEEPL_API_URL")
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or_else(|_| DeepLProvider::default_base_url(&deepl_api_key).to_string());
+            info!("Using DeepL API at {}", deepl_base_url);
+
+            Ok(Arc::new(DeepLProvider::new(
+                client,
+                deepl_api_key,
+                deepl_base_url,
+            )))
+        }
+        "libretranslate" => {
+            let base_url = std::env::var("LIBRETRANSLATE_URL")
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or_else(|_| "http://localhost:5000".to_string());
+            let api_key = std::env::var("LIBRETRANSLATE_API_KEY").ok();
+            info!("Using LibreTranslate API at {}", base_url);
+
+            Ok(Arc::new(LibreTranslateProvider::new(
+                client, base_url, api_key,
+            )))
+        }
+        other => anyhow::bail!(
+            "Unknown TRANSLATION_PROVIDER '{}', expected 'deepl' or 'libretranslate'",
+            other
+        ),
+    }
+}
+
+// CORS_ALLOWED_ORIGINS (comma-separated) restricts cross-origin callers; the
+// bundled frontend is same-origin and does not need it
+fn cors_layer() -> CorsLayer {
+    let Ok(origins) = std::env::var("CORS_ALLOWED_ORIGINS") else {
+        return CorsLayer::permissive();
+    };
+    let origins: Vec<HeaderValue> = origins
+        .split(',')
+        .map(str::trim)
+        .filter(|o| !o.is_empty())
+        .filter_map(|o| o.parse().ok())
+        .collect();
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(tower_http::cors::Any)
+        .allow_headers(tower_http::cors::Any)
+}
+
+// Resolves on Ctrl+C or SIGTERM (sent by `docker stop` and systemd)
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.ok();
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        if let Ok(mut signal) =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        {
+            signal.recv().await;
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("Shutting down");
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Load environment variables from .env file if it exists
@@ -208,28 +526,152 @@ async fn main() -> anyhow::Result<()> {
     
     tracing_subscriber::fmt::init();
 
-    let deepl_api_key = std::env::var("DEEPL_API_KEY")
-        .unwrap_or_else(|_| "your-deepl-api-key-here".to_string());
+    let circuit_breaker = Arc::new(CircuitBreaker::new(
+        env_or("CIRCUIT_BREAKER_THRESHOLD", 5),
+        Duration::from_secs(env_or("CIRCUIT_BREAKER_COOLDOWN_SECS", 30)),
+    ));
+    let retry_policy = RetryPolicy {
+        max_retries: env_or("UPSTREAM_MAX_RETRIES", RetryPolicy::default().max_retries),
+        ..RetryPolicy::default()
+    };
+    let provider = build_provider(reqwest::Client::new())?;
+
+    let cache = Arc::new(TranslationCache::new(CacheConfig {
+        max_entries: NonZeroUsize::new(env_or("CACHE_MAX_ENTRIES", 10_000))
+            .unwrap_or(NonZeroUsize::MIN),
+        max_text_bytes: env_or("CACHE_MAX_TEXT_BYTES", 10_000),
+        ttl: Duration::from_secs(env_or("CACHE_TTL_SECS", 24 * 60 * 60)),
+        persist_path: std::env::var("CACHE_FILE").ok().map(PathBuf::from),
+    }));
+    if let Err(e) = cache.load().await {
+        warn!("Failed to load persisted translation cache: {}", e);
+    }
+    tokio::spawn({
+        let cache = cache.clone();
+        async move { cache.persist_periodically(Duration::from_secs(300)).await }
+    });
+
+    let admin_token = std::env::var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty());
+    let tokens_file = std::env::var("PROXY_TOKENS_FILE").ok().map(PathBuf::from);
+    let default_quota = Quota {
+        daily: env_opt("DEFAULT_DAILY_CHAR_LIMIT"),
+        monthly: env_opt("DEFAULT_MONTHLY_CHAR_LIMIT"),
+    };
+    let allow_anonymous = matches!(
+        std::env::var("ALLOW_ANONYMOUS").as_deref(),
+        Ok("1" | "true")
+    );
+    let authenticator = Arc::new(Authenticator::load(
+        tokens_file.as_deref(),
+        admin_token,
+        default_quota,
+        allow_anonymous,
+    )?);
 
-    if deepl_api_key == "your-deepl-api-key-here" {
-        warn!("Using placeholder API key. Set DEEPL_API_KEY environment variable.");
+    let usage = Arc::new(UsageTracker::new(
+        std::env::var("USAGE_FILE").ok().map(PathBuf::from),
+    ));
+    if let Err(e) = usage.load().await {
+        warn!("Failed to load persisted usage counters: {}", e);
+    }
+    tokio::spawn({
+        let usage = usage.clone();
+        async move { usage.persist_periodically(Duration::from_secs(60)).await }
+    });
+    if authenticator.is_required() {
+        info!("Client authentication enabled ({} tokens)", authenticator.user_count());
+    } else {
+        warn!("ALLOW_ANONYMOUS is set: every endpoint except /admin is open to anyone who can reach this server");
     }
 
+    let provider: Arc<dyn TranslationProvider> = Arc::new(ResilientProvider::new(
+        provider,
+        retry_policy,
+        circuit_breaker.clone(),
+    ));
+
+    // Comma-separated fractions of the upstream allowance that trigger a log warning
+    let usage_thresholds = std::env::var("DEEPL_USAGE_WARN_THRESHOLDS")
+        .unwrap_or_else(|_| "0.8,0.9,0.95".to_string())
+        .split(',')
+        .filter_map(|t| t.trim().parse().ok())
+        .collect();
+    let account_usage = Arc::new(AccountUsageMonitor::new(
+        provider.clone(),
+        Duration::from_secs(env_or("DEEPL_USAGE_CACHE_SECS", 60)),
+        usage_thresholds,
+    ));
+    tokio::spawn({
+        let account_usage = account_usage.clone();
+        async move { account_usage.monitor_periodically(Duration::from_secs(300)).await }
+    });
+
+    let languages = Arc::new(LanguageCatalog::new(
+        provider.clone(),
+        Duration::from_secs(env_or("LANGUAGES_CACHE_SECS", 24 * 60 * 60)),
+    ));
+
+    let terminology = match std::env::var("TERMINOLOGY_FILE").ok().filter(|p| !p.is_empty()) {
+        Some(path) => {
+            let terminology = Terminology::load(Path::new(&path))?;
+            info!("Loaded terminology for {} language pairs from {}", terminology.pair_count(), path);
+            terminology
+        }
+        None => Terminology::empty(),
+    };
+    let terminology = Arc::new(terminology);
+
     let state = AppState {
-        client: reqwest::Client::new(),
-        deepl_api_key,
+        provider,
+        circuit_breaker,
+        cache: cache.clone(),
+        authenticator: authenticator.clone(),
+        usage: usage.clone(),
+        account_usage,
+        languages,
+        terminology,
     };
+    info!("Using translation provider: {}", state.provider.name());
 
     // Serve static files from the frontend dist directory
     let static_files_path = std::env::var("STATIC_FILES_PATH").unwrap_or_else(|_| "./frontend/dist".to_string());
     let serve_dir = ServeDir::new(static_files_path);
 
-    let app = Router::new()
+    // Everything that reaches the upstream or changes state needs a token
+    let protected = Router::new()
         .route("/translate", post(translate_text))
+        .route("/translate/batch", post(translate_batch))
+        .route("/translate/subtitles", post(files::translate_subtitles))
+        .route("/translate/po", post(files::translate_po))
+        .route("/translate/resources", post(files::translate_resources))
+        .route("/translate/xliff", post(files::translate_xliff))
+        .route("/translate/fluent", post(files::translate_fluent))
         .route("/improve", post(improve_text))
-        .route("/health", axum::routing::get(health_check))
+        .route("/languages", get(list_languages))
+        .route(
+            "/glossaries",
+            get(glossaries::list_glossaries).post(glossaries::create_glossary),
+        )
+        .route(
+            "/glossaries/:glossary_id",
+            get(glossaries::glossary).delete(glossaries::delete_glossary),
+        )
+        .route("/glossaries/:glossary_id/entries", get(glossaries::glossary_entries))
+        .route("/auth/me", get(auth_me))
+        .route("/usage", get(user_usage))
+        .route("/usage/deepl", get(provider_usage))
+        .route("/admin/cache", get(cache_stats))
+        .route("/admin/cache", delete(purge_cache))
+        .route_layer(middleware::from_fn_with_state(
+            authenticator,
+            auth::require_auth,
+        ));
+
+    let app = Router::new()
+        .merge(protected)
+        .route("/health", get(health_check))
         .fallback_service(get_service(serve_dir))
-        .layer(CorsLayer::permissive())
+        .layer(cors_layer())
         .with_state(state);
 
     let bind_addr = std::env::var("BIND_ADDRESS").unwrap_or_else(|_| "0.0.0.0".to_string());
@@ -239,6 +681,15 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     info!("Backend server running on http://{}", addr);
     
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    if let Err(e) = cache.save().await {
+        warn!("Failed to persist translation cache: {}", e);
+    }
+    if let Err(e) = usage.save().await {
+        warn!("Failed to persist usage counters: {}", e);
+    }
     Ok(())
 } 
\ No newline at end of file
@@ -0,0 +1,264 @@
+use crate::placeholders::{self, Piece, ProtectedText};
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+/// A Markdown document split into the prose worth translating. Everything
+/// between the segments (code blocks, front matter, HTML blocks, list and
+/// heading markers, ...) is copied through unchanged.
+pub struct MarkdownDocument<'a> {
+    source: &'a str,
+    ranges: Vec<Range<usize>>,
+    /// One per paragraph, heading, table cell, ... with inline code, link
+    /// targets and inline HTML masked
+    pub segments: Vec<ProtectedText>,
+}
+
+enum LineBreak {
+    Soft,
+    Hard,
+}
+
+// Inline content of one block, read from the parser's source offsets
+struct SegmentBuilder<'a> {
+    source: &'a str,
+    range: Option<Range<usize>>,
+    // End of the source already turned into pieces
+    pos: usize,
+    pieces: Vec<Piece>,
+    // Ends of the links being read
+    open_links: Vec<usize>,
+    // Start of a link whose "[" is masked once its text starts
+    link_start: Option<usize>,
+    // End of an autolink whose events are skipped
+    skip_to: Option<usize>,
+    line_break: Option<LineBreak>,
+}
+
+fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS
+        | Options::ENABLE_MATH
+}
+
+// Line prefixes inside block quotes and list items
+fn is_line_prefix(text: &str) -> bool {
+    text.chars().all(|c| c.is_whitespace() || c == '>')
+}
+
+impl<'a> SegmentBuilder<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            range: None,
+            pos: 0,
+            pieces: Vec::new(),
+            open_links: Vec::new(),
+            link_start: None,
+            skip_to: None,
+            line_break: None,
+        }
+    }
+
+    fn text(&mut self, range: Range<usize>) {
+        self.pieces
+            .push(Piece::Text(self.source[range.clone()].to_string()));
+        self.pos = range.end;
+    }
+
+    fn placeholder(&mut self, range: Range<usize>) {
+        self.pieces
+            .push(Piece::Placeholder(self.source[range.clone()].to_string()));
+        self.pos = range.end;
+    }
+
+    // Source between the previous event and `until`: emphasis delimiters,
+    // escapes, and the line prefix after a line break
+    fn gap(&mut self, until: usize) {
+        let line_break = self.line_break.take();
+        if until <= self.pos {
+            return;
+        }
+        let range = self.pos..until;
+        match line_break {
+            // The lines are joined, so the prefix goes away
+            Some(LineBreak::Soft) if is_line_prefix(&self.source[range.clone()]) => {
+                self.pos = until
+            }
+            Some(LineBreak::Hard) if is_line_prefix(&self.source[range.clone()]) => {
+                self.placeholder(range)
+            }
+            _ => self.text(range),
+        }
+    }
+
+    fn event(&mut self, event: &Event, range: Range<usize>) {
+        if let Some(end) = self.skip_to {
+            if range.start < end {
+                return;
+            }
+            self.skip_to = None;
+        }
+        match &mut self.range {
+            Some(segment) => segment.end = segment.end.max(range.end),
+            None => {
+                self.range = Some(range.clone());
+                self.pos = range.start;
+            }
+        }
+        if let Some(start) = self.link_start.take() {
+            self.placeholder(start..range.start);
+        }
+
+        match event {
+            Event::Start(Tag::Link {
+                link_type: LinkType::Autolink | LinkType::Email,
+                ..
+            }) => {
+                self.gap(range.start);
+                self.placeholder(range.clone());
+                self.skip_to = Some(range.end);
+            }
+            Event::Start(Tag::Link { .. } | Tag::Image { .. }) => {
+                self.gap(range.start);
+                self.link_start = Some(range.start);
+                self.open_links.push(range.end);
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                // "](target)" or "][label]"
+                let end = self.open_links.pop().unwrap_or(range.end);
+                self.placeholder(self.pos..end);
+            }
+            Event::Start(_) => self.gap(range.start),
+            Event::End(_) => self.gap(range.end),
+            Event::Text(_) => {
+                self.gap(range.start);
+                self.text(range);
+            }
+            Event::SoftBreak => {
+                self.gap(range.start);
+                self.pieces.push(Piece::Text(" ".to_string()));
+                self.pos = range.end;
+                self.line_break = Some(LineBreak::Soft);
+            }
+            Event::HardBreak => {
+                self.gap(range.start);
+                self.placeholder(range);
+                self.line_break = Some(LineBreak::Hard);
+            }
+            _ => {
+                self.gap(range.start);
+                self.placeholder(range);
+            }
+        }
+    }
+
+    // The segment, unless it has nothing to translate
+    fn finish(&mut self, protect_placeholders: bool) -> Option<(Range<usize>, ProtectedText)> {
+        let builder = std::mem::replace(self, Self::new(self.source));
+        let range = builder.range?;
+        let mut pieces = builder.pieces;
+        if protect_placeholders {
+            pieces = pieces
+                .into_iter()
+                .flat_map(|piece| match piece {
+                    Piece::Text(text) => placeholders::split(&text),
+                    placeholder => vec![placeholder],
+                })
+                .collect();
+        }
+        let segment = ProtectedText::from_pieces(pieces);
+        segment.has_prose().then_some((range, segment))
+    }
+}
+
+// Inline elements continue the current segment; anything else ends it
+fn is_inline(event: &Event) -> bool {
+    match event {
+        Event::Start(tag) => matches!(
+            tag,
+            Tag::Emphasis
+                | Tag::Strong
+                | Tag::Strikethrough
+                | Tag::Superscript
+                | Tag::Subscript
+                | Tag::Link { .. }
+                | Tag::Image { .. }
+        ),
+        Event::End(tag) => matches!(
+            tag,
+            TagEnd::Emphasis
+                | TagEnd::Strong
+                | TagEnd::Strikethrough
+                | TagEnd::Superscript
+                | TagEnd::Subscript
+                | TagEnd::Link
+                | TagEnd::Image
+        ),
+        Event::Text(_)
+        | Event::Code(_)
+        | Event::InlineMath(_)
+        | Event::DisplayMath(_)
+        | Event::InlineHtml(_)
+        | Event::FootnoteReference(_)
+        | Event::SoftBreak
+        | Event::HardBreak => true,
+        Event::Html(_) | Event::Rule | Event::TaskListMarker(_) => false,
+    }
+}
+
+/// Splits `source` into segments. With `protect_placeholders` the segments
+/// also mask format-string placeholders, see [`placeholders::protect`].
+pub fn parse(source: &str, protect_placeholders: bool) -> MarkdownDocument<'_> {
+    let mut document = MarkdownDocument {
+        source,
+        ranges: Vec::new(),
+        segments: Vec::new(),
+    };
+    let mut builder = SegmentBuilder::new(source);
+    // Depth of code blocks, HTML blocks and front matter, whose text is kept
+    let mut verbatim = 0usize;
+
+    for (event, range) in Parser::new_ext(source, options()).into_offset_iter() {
+        match &event {
+            Event::Start(Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_)) => {
+                verbatim += 1
+            }
+            Event::End(TagEnd::CodeBlock | TagEnd::HtmlBlock | TagEnd::MetadataBlock(_)) => {
+                verbatim = verbatim.saturating_sub(1)
+            }
+            _ => {}
+        }
+        if verbatim == 0 && is_inline(&event) {
+            builder.event(&event, range);
+        } else if let Some((range, segment)) = builder.finish(protect_placeholders) {
+            document.ranges.push(range);
+            document.segments.push(segment);
+        }
+    }
+    if let Some((range, segment)) = builder.finish(protect_placeholders) {
+        document.ranges.push(range);
+        document.segments.push(segment);
+    }
+    document
+}
+
+impl MarkdownDocument<'_> {
+    /// The source with each segment replaced by its translation, given in
+    /// segment order with the placeholders already restored
+    pub fn reassemble(&self, translations: &[String]) -> String {
+        let mut output = String::with_capacity(self.source.len());
+        let mut pos = 0;
+        for (range, translation) in self.ranges.iter().zip(translations) {
+            output.push_str(&self.source[pos..range.start]);
+            output.push_str(translation);
+            pos = range.end;
+        }
+        output.push_str(&self.source[pos..]);
+        output
+    }
+}
@@ -0,0 +1,147 @@
+use common::{ApiError, TagHandling, TagOptions};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+
+// Elements that never have a closing tag in HTML
+const HTML_VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum TagKind {
+    Open,
+    Close,
+    SelfClosing,
+}
+
+fn tag_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"<(/?)([A-Za-z_][\w:.-]*)(?:\s+(?:[^>"']|"[^"]*"|'[^']*')*)?\s*(/?)>"#)
+            .expect("valid tag pattern")
+    })
+}
+
+fn comment_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?s)<!--.*?-->|<!\[CDATA\[.*?\]\]>").expect("valid comment pattern")
+    })
+}
+
+// Element names and kinds in document order, ignoring comments and CDATA
+fn tags(text: &str, mode: TagHandling) -> Vec<(String, TagKind)> {
+    let text = comment_pattern().replace_all(text, "");
+    tag_pattern()
+        .captures_iter(&text)
+        .map(|caps| {
+            let name = match mode {
+                TagHandling::Html => caps[2].to_ascii_lowercase(),
+                TagHandling::Xml => caps[2].to_string(),
+            };
+            let kind = if &caps[1] == "/" {
+                TagKind::Close
+            } else if &caps[3] == "/"
+                || (mode == TagHandling::Html && HTML_VOID_ELEMENTS.contains(&name.as_str()))
+            {
+                TagKind::SelfClosing
+            } else {
+                TagKind::Open
+            };
+            (name, kind)
+        })
+        .collect()
+}
+
+// First element closed without being opened, or left open
+fn nesting_error(tags: &[(String, TagKind)], mode: TagHandling) -> Option<String> {
+    let mut open: Vec<&str> = Vec::new();
+    for (name, kind) in tags {
+        match kind {
+            TagKind::Open => open.push(name),
+            TagKind::SelfClosing => {}
+            TagKind::Close => {
+                if open.last() == Some(&name.as_str()) {
+                    open.pop();
+                } else if mode == TagHandling::Html && HTML_VOID_ELEMENTS.contains(&name.as_str()) {
+                    // "</br>" is tolerated by browsers
+                } else {
+                    return Some(format!("</{}> does not match an open element", name));
+                }
+            }
+        }
+    }
+    open.last()
+        .map(|name| format!("<{}> is never closed", name))
+}
+
+/// Checks that `translated` has the same tags as `source` (the upstream may
+/// reorder them along with the words) and nests them properly whenever
+/// `source` did.
+pub fn check_tags(source: &str, translated: &str, mode: TagHandling) -> Result<(), ApiError> {
+    let source_tags = tags(source, mode);
+    let translated_tags = tags(translated, mode);
+
+    let count = |tags: &[(String, TagKind)]| {
+        let mut counts: BTreeMap<(String, TagKind), usize> = BTreeMap::new();
+        for tag in tags {
+            *counts.entry(tag.clone()).or_default() += 1;
+        }
+        counts
+    };
+    let expected = count(&source_tags);
+    let received = count(&translated_tags);
+    if expected != received {
+        let all_tags: BTreeSet<&(String, TagKind)> =
+            expected.keys().chain(received.keys()).collect();
+        let differences: Vec<String> = all_tags
+            .into_iter()
+            .filter(|tag| expected.get(*tag) != received.get(*tag))
+            .map(|tag| {
+                let (name, kind) = tag;
+                let shown = match kind {
+                    TagKind::Open => format!("<{}>", name),
+                    TagKind::Close => format!("</{}>", name),
+                    TagKind::SelfClosing => format!("<{}/>", name),
+                };
+                format!(
+                    "{} {}x instead of {}x",
+                    shown,
+                    received.get(tag).copied().unwrap_or(0),
+                    expected.get(tag).copied().unwrap_or(0)
+                )
+            })
+            .collect();
+        return Err(ApiError::OutputCheckFailed(format!(
+            "Markup was not preserved: {}",
+            differences.join(", ")
+        )));
+    }
+
+    if nesting_error(&source_tags, mode).is_none() {
+        if let Some(error) = nesting_error(&translated_tags, mode) {
+            return Err(ApiError::OutputCheckFailed(format!(
+                "Markup is no longer well-formed: {}",
+                error
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The tag lists and outline detection only mean something with `tag_handling`
+pub fn validate_tag_options(tags: &TagOptions) -> Result<(), ApiError> {
+    let uses_tag_options = !tags.ignore_tags.is_empty()
+        || !tags.non_splitting_tags.is_empty()
+        || !tags.splitting_tags.is_empty()
+        || tags.outline_detection.is_some();
+    if uses_tag_options && tags.tag_handling.is_none() {
+        return Err(ApiError::BadInput(
+            "ignore_tags, non_splitting_tags, splitting_tags and outline_detection require tag_handling"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,328 @@
+use common::ApiError;
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+/// Text with its format-string placeholders replaced by tokens the upstream
+/// leaves alone. ICU plural and select arguments keep their messages
+/// translatable; only the surrounding syntax is masked.
+#[derive(Clone)]
+pub struct ProtectedText {
+    pub text: String,
+    /// Original placeholder for each token, by token index
+    placeholders: Vec<String>,
+    /// Applied to the translated text around the tokens when restoring
+    escape: Option<fn(&str) -> String>,
+}
+
+/// Part of a text to translate
+pub enum Piece {
+    Text(String),
+    /// Kept away from the upstream
+    Placeholder(String),
+}
+
+// Same brackets as the terminology placeholders, with a different letter
+fn token(index: usize) -> String {
+    format!("⟦P{}⟧", index)
+}
+
+fn token_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"⟦\s*P(\d+)\s*⟧").expect("valid token pattern"))
+}
+
+// %s, %d, %1$d, %.2f, %-5s, %ld, %@, %(name)s and a literal %%
+fn printf_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"%(?:\d+\$)?[-+0#]*\d*(?:\.\d+)?(?:ll|l|h)?[sdifuxXoeEgGcp@]|%\([\w.]+\)[sdifr]|%%",
+        )
+        .expect("valid printf pattern")
+    })
+}
+
+// {{name}}, {{{raw}}}, {{#each items}}
+fn mustache_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(?:\{\{\{[^{}\n]*\}\}\}|\{\{[^{}\n]*\}\})").expect("valid mustache pattern")
+    })
+}
+
+// {name}, {0}, { $name }, ${name}, {count, number}, {when, date, short}
+fn argument_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^\$?\{\s*[\w.$-]+\s*(?:,[^{}]*)?\}").expect("valid argument pattern")
+    })
+}
+
+// "{count, plural, offset:1 " up to the first selector
+fn complex_argument_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^\{\s*[\w.$-]+\s*,\s*(plural|selectordinal|select)\s*,\s*(?:offset:\d+\s*)?")
+            .expect("valid complex argument pattern")
+    })
+}
+
+// "one {", "=0 {", "other {"
+fn selector_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^(?:=\d+|[\w-]+)\s*\{").expect("valid selector pattern"))
+}
+
+fn whitespace_len(text: &str) -> usize {
+    text.len() - text.trim_start().len()
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    pieces: Vec<Piece>,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn push_text(&mut self, text: &str) {
+        match self.pieces.last_mut() {
+            Some(Piece::Text(last)) => last.push_str(text),
+            _ => self.pieces.push(Piece::Text(text.to_string())),
+        }
+    }
+
+    fn push_placeholder(&mut self, placeholder: &str) {
+        match self.pieces.last_mut() {
+            Some(Piece::Placeholder(last)) => last.push_str(placeholder),
+            _ => self
+                .pieces
+                .push(Piece::Placeholder(placeholder.to_string())),
+        }
+    }
+
+    // Consumes `len` bytes as a placeholder
+    fn take_placeholder(&mut self, len: usize) {
+        let placeholder = &self.text[self.pos..self.pos + len];
+        self.push_placeholder(placeholder);
+        self.pos += len;
+    }
+
+    // Reads text until the end, or until the `}` closing a nested message.
+    // `#` stands for the number inside plural messages.
+    fn message(&mut self, nested: bool, in_plural: bool) {
+        while let Some(c) = self.rest().chars().next() {
+            if c == '}' && nested {
+                return;
+            }
+            if c == '#' && in_plural {
+                self.take_placeholder(1);
+                continue;
+            }
+            if c == '{' || c == '$' {
+                if let Some(m) = mustache_pattern().find(self.rest()) {
+                    self.take_placeholder(m.end());
+                    continue;
+                }
+                if let Some(m) = argument_pattern().find(self.rest()) {
+                    self.take_placeholder(m.end());
+                    continue;
+                }
+                if c == '{' && self.complex_argument(in_plural) {
+                    continue;
+                }
+            }
+            let mut buf = [0; 4];
+            self.push_text(c.encode_utf8(&mut buf));
+            self.pos += c.len_utf8();
+        }
+    }
+
+    // An ICU plural/select argument. Leaves everything as it was and returns
+    // false when the syntax is not complete.
+    fn complex_argument(&mut self, in_plural: bool) -> bool {
+        let start = (self.pos, self.pieces.len());
+        let Some(caps) = complex_argument_pattern().captures(self.rest()) else {
+            return false;
+        };
+        let is_plural = &caps[1] != "select";
+        let header_len = caps[0].len();
+        self.take_placeholder(header_len);
+
+        let mut selectors = 0;
+        loop {
+            let spaces = whitespace_len(self.rest());
+            self.take_placeholder(spaces);
+            if self.rest().starts_with('}') && selectors > 0 {
+                self.take_placeholder(1);
+                return true;
+            }
+            let Some(m) = selector_pattern().find(self.rest()) else {
+                break;
+            };
+            self.take_placeholder(m.end());
+            self.message(true, is_plural || in_plural);
+            if !self.rest().starts_with('}') {
+                break;
+            }
+            self.take_placeholder(1);
+            selectors += 1;
+        }
+
+        self.pos = start.0;
+        self.pieces.truncate(start.1);
+        false
+    }
+}
+
+/// Splits `text` into prose and format-string placeholders
+pub fn split(text: &str) -> Vec<Piece> {
+    let mut parser = Parser {
+        text,
+        pos: 0,
+        pieces: Vec::new(),
+    };
+    parser.message(false, false);
+
+    let mut pieces = Vec::new();
+    for piece in parser.pieces {
+        match piece {
+            Piece::Placeholder(_) => pieces.push(piece),
+            Piece::Text(text) => {
+                let mut last = 0;
+                for m in printf_pattern().find_iter(&text) {
+                    pieces.push(Piece::Text(text[last..m.start()].to_string()));
+                    pieces.push(Piece::Placeholder(m.as_str().to_string()));
+                    last = m.end();
+                }
+                pieces.push(Piece::Text(text[last..].to_string()));
+            }
+        }
+    }
+    pieces
+}
+
+/// Masks placeholders in `text`, see [`ProtectedText`]
+pub fn protect(text: &str) -> ProtectedText {
+    ProtectedText::from_pieces(split(text))
+}
+
+impl ProtectedText {
+    /// `text` sent as is
+    pub fn plain(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            placeholders: Vec::new(),
+            escape: None,
+        }
+    }
+
+    /// Has [`Self::restore`] escape the translated text, for formats whose
+    /// placeholders are markup but whose text is not
+    pub fn escaping(mut self, escape: fn(&str) -> String) -> Self {
+        self.escape = Some(escape);
+        self
+    }
+
+    /// Whether anything outside the placeholders is worth translating
+    pub fn has_prose(&self) -> bool {
+        token_pattern()
+            .split(&self.text)
+            .any(|text| text.chars().any(char::is_alphabetic))
+    }
+
+    /// Joins `pieces`, with one token for each run of adjacent placeholders
+    pub fn from_pieces(pieces: impl IntoIterator<Item = Piece>) -> Self {
+        let mut protected = Self::plain("");
+        let mut after_placeholder = false;
+        for piece in pieces {
+            match piece {
+                Piece::Text(text) if !text.is_empty() => {
+                    protected.text.push_str(&text);
+                    after_placeholder = false;
+                }
+                Piece::Placeholder(placeholder) if !placeholder.is_empty() => {
+                    match protected.placeholders.last_mut() {
+                        Some(last) if after_placeholder => last.push_str(&placeholder),
+                        _ => {
+                            protected
+                                .text
+                                .push_str(&token(protected.placeholders.len()));
+                            protected.placeholders.push(placeholder);
+                        }
+                    }
+                    after_placeholder = true;
+                }
+                _ => {}
+            }
+        }
+        protected
+    }
+
+    /// Fails unless every token appears exactly once in `translated`
+    pub fn check(&self, translated: &str) -> Result<(), ApiError> {
+        if self.placeholders.is_empty() {
+            return Ok(());
+        }
+
+        let mut counts = vec![0usize; self.placeholders.len()];
+        for caps in token_pattern().captures_iter(translated) {
+            match caps[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| counts.get_mut(i))
+            {
+                Some(count) => *count += 1,
+                None => {
+                    return Err(ApiError::OutputCheckFailed(format!(
+                        "The translation contains an unknown placeholder {}",
+                        &caps[0]
+                    )))
+                }
+            }
+        }
+
+        let problems: Vec<String> = self
+            .placeholders
+            .iter()
+            .zip(counts)
+            .filter(|(_, count)| *count != 1)
+            .map(|(placeholder, count)| match count {
+                0 => format!("\"{}\" is missing", placeholder),
+                n => format!("\"{}\" appears {} times", placeholder, n),
+            })
+            .collect();
+        if !problems.is_empty() {
+            return Err(ApiError::OutputCheckFailed(format!(
+                "Placeholders were not preserved: {}",
+                problems.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// Puts the original placeholders back into `translated`, see [`Self::check`]
+    pub fn restore(&self, translated: &str) -> Result<String, ApiError> {
+        let translated = match self.escape {
+            Some(escape) => escape(translated),
+            None => translated.to_string(),
+        };
+        if self.placeholders.is_empty() {
+            return Ok(translated);
+        }
+        self.check(&translated)?;
+        Ok(token_pattern()
+            .replace_all(&translated, |caps: &Captures| {
+                caps[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| self.placeholders.get(i))
+                    .cloned()
+                    .unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned())
+    }
+}
@@ -0,0 +1,344 @@
+use super::{
+    read_json, read_text, AccountUsage, ImproveOptions, Improvement, ProviderError,
+    TranslateOptions, Translation, TranslationProvider,
+};
+use async_trait::async_trait;
+use common::{CreateGlossaryRequest, GlossaryInfo, Language, LanguagesResponse, TagHandling};
+use serde::{Deserialize, Serialize};
+
+pub const DEEPL_FREE_API_URL: &str = "https://api-free.deepl.com";
+pub const DEEPL_PRO_API_URL: &str = "https://api.deepl.com";
+
+pub struct DeepLProvider {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct DeepLTranslateResponse {
+    translations: Vec<DeepLTranslation>,
+}
+
+#[derive(Deserialize)]
+struct DeepLTranslation {
+    detected_source_language: Option<String>,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct DeepLTranslateRequest<'a> {
+    text: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_lang: Option<&'a str>,
+    target_lang: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    formality: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    glossary_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag_handling: Option<TagHandling>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    ignore_tags: &'a [String],
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    non_splitting_tags: &'a [String],
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    splitting_tags: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outline_detection: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct DeepLImproveRequest<'a> {
+    text: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_lang: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    writing_style: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tone: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct DeepLImproveResponse {
+    improvements: Vec<DeepLImprovement>,
+}
+
+#[derive(Deserialize)]
+struct DeepLImprovement {
+    detected_source_language: Option<String>,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct DeepLUsageResponse {
+    character_count: u64,
+    character_limit: u64,
+}
+
+#[derive(Deserialize)]
+struct DeepLLanguage {
+    language: String,
+    name: String,
+    // Only reported for target languages
+    #[serde(default)]
+    supports_formality: bool,
+}
+
+#[derive(Serialize)]
+struct DeepLCreateGlossaryRequest<'a> {
+    name: &'a str,
+    source_lang: &'a str,
+    target_lang: &'a str,
+    entries: &'a str,
+    entries_format: &'a str,
+}
+
+#[derive(Deserialize)]
+struct DeepLGlossaryList {
+    glossaries: Vec<DeepLGlossary>,
+}
+
+#[derive(Deserialize)]
+struct DeepLGlossary {
+    glossary_id: String,
+    name: String,
+    ready: bool,
+    source_lang: String,
+    target_lang: String,
+    creation_time: String,
+    entry_count: u64,
+}
+
+impl From<DeepLGlossary> for GlossaryInfo {
+    // DeepL reports glossary languages in lowercase, unlike everywhere else
+    fn from(g: DeepLGlossary) -> Self {
+        GlossaryInfo {
+            glossary_id: g.glossary_id,
+            name: g.name,
+            ready: g.ready,
+            source_lang: g.source_lang.to_uppercase(),
+            target_lang: g.target_lang.to_uppercase(),
+            creation_time: g.creation_time,
+            entry_count: g.entry_count,
+        }
+    }
+}
+
+impl DeepLProvider {
+    pub fn new(client: reqwest::Client, api_key: String, base_url: String) -> Self {
+        Self {
+            client,
+            api_key,
+            base_url,
+        }
+    }
+
+    // Free API keys end with ":fx", everything else is a Pro key
+    pub fn default_base_url(api_key: &str) -> &'static str {
+        if api_key.ends_with(":fx") {
+            DEEPL_FREE_API_URL
+        } else {
+            DEEPL_PRO_API_URL
+        }
+    }
+
+    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .post(format!("{}{}", self.base_url, path))
+            .header("Authorization", format!("DeepL-Auth-Key {}", self.api_key))
+    }
+
+    fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .get(format!("{}{}", self.base_url, path))
+            .header("Authorization", format!("DeepL-Auth-Key {}", self.api_key))
+    }
+
+    fn delete(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .delete(format!("{}{}", self.base_url, path))
+            .header("Authorization", format!("DeepL-Auth-Key {}", self.api_key))
+    }
+
+    async fn fetch_languages(&self, kind: &str) -> Result<Vec<Language>, ProviderError> {
+        let response = self
+            .get("/v2/languages")
+            .query(&[("type", kind)])
+            .send()
+            .await
+            .map_err(ProviderError::Network)?;
+        let languages: Vec<DeepLLanguage> = read_json(response).await?;
+
+        Ok(languages
+            .into_iter()
+            .map(|l| Language {
+                code: l.language.to_uppercase(),
+                name: l.name,
+                supports_formality: l.supports_formality,
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl TranslationProvider for DeepLProvider {
+    fn name(&self) -> &'static str {
+        "deepl"
+    }
+
+    async fn translate(
+        &self,
+        texts: Vec<String>,
+        options: &TranslateOptions,
+    ) -> Result<Vec<Translation>, ProviderError> {
+        let deepl_req = DeepLTranslateRequest {
+            text: texts,
+            source_lang: options.source_lang.as_deref(),
+            target_lang: &options.target_lang,
+            formality: options.formality.as_deref(),
+            glossary_id: options.glossary_id.as_deref(),
+            tag_handling: options.tags.tag_handling,
+            ignore_tags: &options.tags.ignore_tags,
+            non_splitting_tags: &options.tags.non_splitting_tags,
+            splitting_tags: &options.tags.splitting_tags,
+            outline_detection: options.tags.outline_detection,
+            context: options.context.as_deref(),
+        };
+
+        let response = self
+            .post("/v2/translate")
+            .json(&deepl_req)
+            .send()
+            .await
+            .map_err(ProviderError::Network)?;
+        let deepl_resp: DeepLTranslateResponse = read_json(response).await?;
+
+        Ok(deepl_resp
+            .translations
+            .into_iter()
+            .map(|t| Translation {
+                text: t.text,
+                detected_source_language: t.detected_source_language,
+            })
+            .collect())
+    }
+
+    async fn improve(
+        &self,
+        texts: Vec<String>,
+        options: &ImproveOptions,
+    ) -> Result<Vec<Improvement>, ProviderError> {
+        let deepl_req = DeepLImproveRequest {
+            text: texts,
+            target_lang: options.target_lang.as_deref(),
+            writing_style: options.writing_style.as_deref(),
+            tone: options.tone.as_deref(),
+        };
+
+        let response = self
+            .post("/v2/write/rephrase")
+            .json(&deepl_req)
+            .send()
+            .await
+            .map_err(ProviderError::Network)?;
+        let deepl_resp: DeepLImproveResponse = read_json(response).await?;
+
+        Ok(deepl_resp
+            .improvements
+            .into_iter()
+            .map(|i| Improvement {
+                text: i.text,
+                detected_source_language: i.detected_source_language,
+            })
+            .collect())
+    }
+
+    async fn account_usage(&self) -> Result<AccountUsage, ProviderError> {
+        let response = self
+            .get("/v2/usage")
+            .send()
+            .await
+            .map_err(ProviderError::Network)?;
+        let usage: DeepLUsageResponse = read_json(response).await?;
+
+        Ok(AccountUsage {
+            character_count: usage.character_count,
+            character_limit: usage.character_limit,
+        })
+    }
+
+    async fn languages(&self) -> Result<LanguagesResponse, ProviderError> {
+        Ok(LanguagesResponse {
+            source: self.fetch_languages("source").await?,
+            target: self.fetch_languages("target").await?,
+        })
+    }
+
+    async fn create_glossary(
+        &self,
+        glossary: &CreateGlossaryRequest,
+    ) -> Result<GlossaryInfo, ProviderError> {
+        let deepl_req = DeepLCreateGlossaryRequest {
+            name: &glossary.name,
+            source_lang: &glossary.source_lang,
+            target_lang: &glossary.target_lang,
+            entries: &glossary.entries,
+            entries_format: glossary.entries_format.as_str(),
+        };
+
+        let response = self
+            .post("/v2/glossaries")
+            .json(&deepl_req)
+            .send()
+            .await
+            .map_err(ProviderError::Network)?;
+        let created: DeepLGlossary = read_json(response).await?;
+        Ok(created.into())
+    }
+
+    async fn list_glossaries(&self) -> Result<Vec<GlossaryInfo>, ProviderError> {
+        let response = self
+            .get("/v2/glossaries")
+            .send()
+            .await
+            .map_err(ProviderError::Network)?;
+        let list: DeepLGlossaryList = read_json(response).await?;
+        Ok(list
+            .glossaries
+            .into_iter()
+            .map(GlossaryInfo::from)
+            .collect())
+    }
+
+    async fn glossary(&self, glossary_id: &str) -> Result<GlossaryInfo, ProviderError> {
+        let response = self
+            .get(&format!("/v2/glossaries/{}", glossary_id))
+            .send()
+            .await
+            .map_err(ProviderError::Network)?;
+        let glossary: DeepLGlossary = read_json(response).await?;
+        Ok(glossary.into())
+    }
+
+    async fn glossary_entries(&self, glossary_id: &str) -> Result<String, ProviderError> {
+        let response = self
+            .get(&format!("/v2/glossaries/{}/entries", glossary_id))
+            .header("Accept", "text/tab-separated-values")
+            .send()
+            .await
+            .map_err(ProviderError::Network)?;
+        read_text(response).await
+    }
+
+    async fn delete_glossary(&self, glossary_id: &str) -> Result<(), ProviderError> {
+        let response = self
+            .delete(&format!("/v2/glossaries/{}", glossary_id))
+            .send()
+            .await
+            .map_err(ProviderError::Network)?;
+        read_text(response).await.map(|_| ())
+    }
+}
@@ -0,0 +1,136 @@
+use super::{read_json, ProviderError, TranslateOptions, Translation, TranslationProvider};
+use async_trait::async_trait;
+use common::{Language, LanguagesResponse, TagHandling};
+use serde::{Deserialize, Serialize};
+
+/// Talks to a LibreTranslate-compatible server, e.g. a self-hosted instance
+/// used when the DeepL quota is exhausted.
+pub struct LibreTranslateProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LibreTranslateRequest<'a> {
+    q: Vec<String>,
+    source: String,
+    target: String,
+    format: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibreTranslateResponse {
+    translated_text: Vec<String>,
+    // Only present when the source language was "auto"
+    #[serde(default)]
+    detected_language: Vec<LibreDetectedLanguage>,
+}
+
+#[derive(Deserialize)]
+struct LibreDetectedLanguage {
+    language: String,
+}
+
+#[derive(Deserialize)]
+struct LibreLanguage {
+    code: String,
+    name: String,
+}
+
+impl LibreTranslateProvider {
+    pub fn new(client: reqwest::Client, base_url: String, api_key: Option<String>) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+        }
+    }
+}
+
+// LibreTranslate uses lowercase ISO 639-1 codes without region ("EN-US" -> "en")
+fn to_libre_lang(code: &str) -> String {
+    code.split('-').next().unwrap_or(code).to_lowercase()
+}
+
+#[async_trait]
+impl TranslationProvider for LibreTranslateProvider {
+    fn name(&self) -> &'static str {
+        "libretranslate"
+    }
+
+    async fn translate(
+        &self,
+        texts: Vec<String>,
+        options: &TranslateOptions,
+    ) -> Result<Vec<Translation>, ProviderError> {
+        // Translating without the requested terminology would look like success
+        if options.glossary_id.is_some() {
+            return Err(ProviderError::Unsupported("glossaries"));
+        }
+        // HTML is understood, but neither XML nor DeepL's tag lists are
+        let format = match options.tags.tag_handling {
+            None => "text",
+            Some(TagHandling::Html) => "html",
+            Some(TagHandling::Xml) => return Err(ProviderError::Unsupported("XML tag handling")),
+        };
+
+        let libre_req = LibreTranslateRequest {
+            q: texts,
+            source: options
+                .source_lang
+                .as_deref()
+                .map(to_libre_lang)
+                .unwrap_or_else(|| "auto".to_string()),
+            target: to_libre_lang(&options.target_lang),
+            format,
+            api_key: self.api_key.as_deref(),
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/translate", self.base_url))
+            .json(&libre_req)
+            .send()
+            .await
+            .map_err(ProviderError::Network)?;
+        let libre_resp: LibreTranslateResponse = read_json(response).await?;
+
+        let mut detected = libre_resp.detected_language.into_iter();
+        Ok(libre_resp
+            .translated_text
+            .into_iter()
+            .map(|text| Translation {
+                text,
+                detected_source_language: detected.next().map(|d| d.language.to_uppercase()),
+            })
+            .collect())
+    }
+
+    async fn languages(&self) -> Result<LanguagesResponse, ProviderError> {
+        let response = self
+            .client
+            .get(format!("{}/languages", self.base_url))
+            .send()
+            .await
+            .map_err(ProviderError::Network)?;
+        let languages: Vec<LibreLanguage> = read_json(response).await?;
+
+        // Codes are upper-cased to match the DeepL convention used by the proxy
+        let languages: Vec<Language> = languages
+            .into_iter()
+            .map(|l| Language {
+                code: l.code.to_uppercase(),
+                name: l.name,
+                supports_formality: false,
+            })
+            .collect();
+        Ok(LanguagesResponse {
+            source: languages.clone(),
+            target: languages,
+        })
+    }
+}
@@ -0,0 +1,217 @@
+use async_trait::async_trait;
+use common::{CreateGlossaryRequest, GlossaryInfo, LanguagesResponse, TagOptions};
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+mod deepl;
+mod libretranslate;
+mod resilient;
+
+pub use deepl::DeepLProvider;
+pub use libretranslate::LibreTranslateProvider;
+pub use resilient::{CircuitBreaker, CircuitState, ResilientProvider, RetryPolicy};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TranslateOptions {
+    pub source_lang: Option<String>,
+    pub target_lang: String,
+    pub formality: Option<String>,
+    pub glossary_id: Option<String>,
+    pub tags: TagOptions,
+    /// Text that helps the upstream pick a meaning, such as a gettext msgctxt.
+    /// It is neither translated nor billed. Left out of cache keys when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Translation {
+    pub text: String,
+    pub detected_source_language: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImproveOptions {
+    pub target_lang: Option<String>,
+    pub writing_style: Option<String>,
+    pub tone: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Improvement {
+    pub text: String,
+    pub detected_source_language: Option<String>,
+}
+
+/// Characters used in the upstream account's current billing period
+#[derive(Debug, Clone)]
+pub struct AccountUsage {
+    pub character_count: u64,
+    pub character_limit: u64,
+}
+
+#[derive(Debug)]
+pub enum ProviderError {
+    /// The request never reached the upstream or the connection failed
+    Network(reqwest::Error),
+    /// The upstream answered with a non-success status
+    Status {
+        status: reqwest::StatusCode,
+        body: String,
+        retry_after: Option<Duration>,
+    },
+    /// The upstream answered with a body we could not decode
+    Parse(reqwest::Error),
+    /// The upstream returned fewer or more results than texts sent
+    IncompleteResponse { expected: usize, received: usize },
+    /// The provider does not implement this operation
+    Unsupported(&'static str),
+    /// The circuit breaker is open; the upstream was not called
+    CircuitOpen { retry_in: Duration },
+}
+
+impl ProviderError {
+    /// Whether the same request may succeed if sent again later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ProviderError::Network(_) => true,
+            ProviderError::Status { status, .. } => {
+                status.as_u16() == 429 || status.is_server_error()
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the failure suggests the upstream itself is down, as opposed
+    /// to throttling us or rejecting this particular request
+    pub fn is_outage(&self) -> bool {
+        match self {
+            ProviderError::Network(_) => true,
+            ProviderError::Status { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderError::Network(e) => write!(f, "request failed: {}", e),
+            ProviderError::Status { status, body, .. } => {
+                write!(f, "API error: {} - {}", status, body)
+            }
+            ProviderError::Parse(e) => write!(f, "failed to parse response: {}", e),
+            ProviderError::IncompleteResponse { expected, received } => write!(
+                f,
+                "expected {} results from upstream, received {}",
+                expected, received
+            ),
+            ProviderError::Unsupported(what) => write!(f, "unsupported operation: {}", what),
+            ProviderError::CircuitOpen { retry_in } => {
+                write!(f, "circuit breaker open, retry in {}s", retry_in.as_secs())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+/// An upstream machine translation engine. Handlers only talk to this trait,
+/// so the proxy can run against DeepL or a self-hosted engine.
+#[async_trait]
+pub trait TranslationProvider: Send + Sync {
+    /// Short identifier used in logs and on `/health`
+    fn name(&self) -> &'static str;
+
+    /// Translates every text in `texts`, returning results in the same order
+    async fn translate(
+        &self,
+        texts: Vec<String>,
+        options: &TranslateOptions,
+    ) -> Result<Vec<Translation>, ProviderError>;
+
+    /// Rephrases every text in `texts`, returning results in the same order
+    async fn improve(
+        &self,
+        _texts: Vec<String>,
+        _options: &ImproveOptions,
+    ) -> Result<Vec<Improvement>, ProviderError> {
+        Err(ProviderError::Unsupported("text improvement"))
+    }
+
+    /// Reports how much of the upstream account's allowance is used
+    async fn account_usage(&self) -> Result<AccountUsage, ProviderError> {
+        Err(ProviderError::Unsupported("account usage"))
+    }
+
+    /// Lists the languages accepted as source and as target
+    async fn languages(&self) -> Result<LanguagesResponse, ProviderError> {
+        Err(ProviderError::Unsupported("language listing"))
+    }
+
+    async fn create_glossary(
+        &self,
+        _glossary: &CreateGlossaryRequest,
+    ) -> Result<GlossaryInfo, ProviderError> {
+        Err(ProviderError::Unsupported("glossaries"))
+    }
+
+    async fn list_glossaries(&self) -> Result<Vec<GlossaryInfo>, ProviderError> {
+        Err(ProviderError::Unsupported("glossaries"))
+    }
+
+    async fn glossary(&self, _glossary_id: &str) -> Result<GlossaryInfo, ProviderError> {
+        Err(ProviderError::Unsupported("glossaries"))
+    }
+
+    /// Returns the glossary's entries as TSV
+    async fn glossary_entries(&self, _glossary_id: &str) -> Result<String, ProviderError> {
+        Err(ProviderError::Unsupported("glossaries"))
+    }
+
+    async fn delete_glossary(&self, _glossary_id: &str) -> Result<(), ProviderError> {
+        Err(ProviderError::Unsupported("glossaries"))
+    }
+}
+
+/// Reads an upstream response, turning non-success statuses and undecodable
+/// bodies into a `ProviderError`.
+pub(crate) async fn read_json<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+) -> Result<T, ProviderError> {
+    check_status(response)
+        .await?
+        .json::<T>()
+        .await
+        .map_err(ProviderError::Parse)
+}
+
+/// Like `read_json`, for plain-text bodies
+pub(crate) async fn read_text(response: reqwest::Response) -> Result<String, ProviderError> {
+    check_status(response)
+        .await?
+        .text()
+        .await
+        .map_err(ProviderError::Parse)
+}
+
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, ProviderError> {
+    let status = response.status();
+    if !status.is_success() {
+        // Only the delay-seconds form of Retry-After is used by DeepL
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let body = response.text().await.unwrap_or_default();
+        return Err(ProviderError::Status {
+            status,
+            body,
+            retry_after,
+        });
+    }
+    Ok(response)
+}
@@ -0,0 +1,308 @@
+use super::{
+    AccountUsage, ImproveOptions, Improvement, ProviderError, TranslateOptions, Translation,
+    TranslationProvider,
+};
+use async_trait::async_trait;
+use common::{CreateGlossaryRequest, GlossaryInfo, LanguagesResponse};
+use rand::Rng;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How transient upstream failures are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    /// Upper bound for a single wait. A `Retry-After` longer than this is not
+    /// waited out; the error is returned to the caller instead.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Exponential backoff with full jitter: a random delay in [0, base * 2^attempt]
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let millis = ceiling.as_millis() as u64;
+        Duration::from_millis(rand::thread_rng().gen_range(0..=millis))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitSnapshot {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    /// Seconds until the next probe request is let through, unless closed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_in_secs: Option<u64>,
+}
+
+struct BreakerInner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Stops calling the upstream after `failure_threshold` consecutive outages.
+/// After `cooldown` a single probe request is let through; its outcome
+/// decides whether the circuit closes again.
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            inner: Mutex::new(BreakerInner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+            }),
+        }
+    }
+
+    /// Returns how long to wait if the call must not go through
+    fn try_acquire(&self) -> Result<(), Duration> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == CircuitState::Closed {
+            return Ok(());
+        }
+
+        // While open, `opened_at` is when the circuit tripped; while half-open it
+        // is when the probe started, so a probe that never reports back (e.g. the
+        // client disconnected) does not keep the circuit stuck.
+        let elapsed = inner.opened_at.map(|t| t.elapsed()).unwrap_or_default();
+        if elapsed >= self.cooldown {
+            info!("Circuit breaker half-open, probing upstream");
+            inner.state = CircuitState::HalfOpen;
+            inner.opened_at = Some(Instant::now());
+            Ok(())
+        } else {
+            Err(self.cooldown - elapsed)
+        }
+    }
+
+    fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state != CircuitState::Closed {
+            info!("Circuit breaker closed, upstream recovered");
+        }
+        inner.state = CircuitState::Closed;
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+    }
+
+    fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+        let trip = inner.state == CircuitState::HalfOpen
+            || inner.consecutive_failures >= self.failure_threshold;
+        if trip && inner.state != CircuitState::Open {
+            warn!(
+                "Circuit breaker opened after {} consecutive failures",
+                inner.consecutive_failures
+            );
+            inner.state = CircuitState::Open;
+            inner.opened_at = Some(Instant::now());
+        }
+    }
+
+    pub fn snapshot(&self) -> CircuitSnapshot {
+        let inner = self.inner.lock().unwrap();
+        let retry_in_secs = match inner.state {
+            CircuitState::Closed => None,
+            _ => inner
+                .opened_at
+                .map(|t| self.cooldown.saturating_sub(t.elapsed()).as_secs()),
+        };
+        CircuitSnapshot {
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            retry_in_secs,
+        }
+    }
+}
+
+/// Wraps another provider with retries and a circuit breaker shared by all
+/// operations.
+pub struct ResilientProvider {
+    inner: Arc<dyn TranslationProvider>,
+    retry: RetryPolicy,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl ResilientProvider {
+    pub fn new(
+        inner: Arc<dyn TranslationProvider>,
+        retry: RetryPolicy,
+        breaker: Arc<CircuitBreaker>,
+    ) -> Self {
+        Self {
+            inner,
+            retry,
+            breaker,
+        }
+    }
+
+    async fn call<T, F, Fut>(&self, op: F) -> Result<T, ProviderError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        self.call_with_retries(self.retry.max_retries, op).await
+    }
+
+    // For calls that are not safe to repeat: a failed attempt may still have
+    // changed state upstream
+    async fn call_once<T, F, Fut>(&self, op: F) -> Result<T, ProviderError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        self.call_with_retries(0, op).await
+    }
+
+    async fn call_with_retries<T, F, Fut>(
+        &self,
+        max_retries: u32,
+        mut op: F,
+    ) -> Result<T, ProviderError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            if let Err(retry_in) = self.breaker.try_acquire() {
+                return Err(ProviderError::CircuitOpen { retry_in });
+            }
+
+            let error = match op().await {
+                Ok(value) => {
+                    self.breaker.record_success();
+                    return Ok(value);
+                }
+                Err(e) => e,
+            };
+
+            if error.is_outage() {
+                self.breaker.record_failure();
+            } else {
+                // The upstream answered, so it is up even if it rejected the request
+                self.breaker.record_success();
+            }
+
+            if !error.is_retryable() || attempt >= max_retries {
+                return Err(error);
+            }
+
+            let delay = match &error {
+                ProviderError::Status {
+                    retry_after: Some(retry_after),
+                    ..
+                } => {
+                    if *retry_after > self.retry.max_delay {
+                        return Err(error);
+                    }
+                    *retry_after
+                }
+                _ => self.retry.backoff(attempt),
+            };
+
+            attempt += 1;
+            warn!(
+                "Upstream call failed ({}), retry {}/{} in {}ms",
+                error,
+                attempt,
+                max_retries,
+                delay.as_millis()
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[async_trait]
+impl TranslationProvider for ResilientProvider {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn translate(
+        &self,
+        texts: Vec<String>,
+        options: &TranslateOptions,
+    ) -> Result<Vec<Translation>, ProviderError> {
+        self.call(|| self.inner.translate(texts.clone(), options))
+            .await
+    }
+
+    async fn improve(
+        &self,
+        texts: Vec<String>,
+        options: &ImproveOptions,
+    ) -> Result<Vec<Improvement>, ProviderError> {
+        self.call(|| self.inner.improve(texts.clone(), options))
+            .await
+    }
+
+    async fn account_usage(&self) -> Result<AccountUsage, ProviderError> {
+        self.call(|| self.inner.account_usage()).await
+    }
+
+    async fn languages(&self) -> Result<LanguagesResponse, ProviderError> {
+        self.call(|| self.inner.languages()).await
+    }
+
+    async fn create_glossary(
+        &self,
+        glossary: &CreateGlossaryRequest,
+    ) -> Result<GlossaryInfo, ProviderError> {
+        self.call_once(|| self.inner.create_glossary(glossary))
+            .await
+    }
+
+    async fn list_glossaries(&self) -> Result<Vec<GlossaryInfo>, ProviderError> {
+        self.call(|| self.inner.list_glossaries()).await
+    }
+
+    async fn glossary(&self, glossary_id: &str) -> Result<GlossaryInfo, ProviderError> {
+        self.call(|| self.inner.glossary(glossary_id)).await
+    }
+
+    async fn glossary_entries(&self, glossary_id: &str) -> Result<String, ProviderError> {
+        self.call(|| self.inner.glossary_entries(glossary_id)).await
+    }
+
+    async fn delete_glossary(&self, glossary_id: &str) -> Result<(), ProviderError> {
+        self.call_once(|| self.inner.delete_glossary(glossary_id))
+            .await
+    }
+}
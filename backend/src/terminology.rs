@@ -0,0 +1,226 @@
+use crate::provider::TranslateOptions;
+use common::AppliedTerm;
+use regex::{Captures, Regex, RegexBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// `{"pairs": [{"source_lang": "EN", "target_lang": "PL", "terms": [{"source": "sign in", "target": "zaloguj się"}]}]}`
+#[derive(Deserialize)]
+struct TerminologyFile {
+    pairs: Vec<PairConfig>,
+}
+
+#[derive(Deserialize)]
+struct PairConfig {
+    source_lang: String,
+    target_lang: String,
+    terms: Vec<TermConfig>,
+}
+
+#[derive(Deserialize)]
+struct TermConfig {
+    source: String,
+    target: String,
+}
+
+struct TermPair {
+    source_lang: String,
+    target_lang: String,
+    /// Matches any source term, longest first, ignoring case
+    pattern: Regex,
+    /// Lowercased source term -> target term
+    targets: HashMap<String, String>,
+}
+
+/// Local terminology enforced around the upstream call: source terms are
+/// replaced by placeholders the upstream leaves alone, and the placeholders by
+/// the configured target terms afterwards. Works with any provider and any
+/// language pair, unlike upstream glossaries.
+pub struct Terminology {
+    pairs: Vec<TermPair>,
+}
+
+/// Text with its terms masked, ready to be sent upstream
+pub struct MaskedText {
+    pub text: String,
+    /// Source text and target term for each placeholder, by placeholder index
+    terms: Vec<AppliedTerm>,
+}
+
+// Unusual brackets so the upstream treats placeholders as opaque tokens
+fn placeholder(index: usize) -> String {
+    format!("⟦T{}⟧", index)
+}
+
+fn placeholder_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"⟦\s*T(\d+)\s*⟧").expect("valid placeholder pattern"))
+}
+
+// Base language of a code, e.g. "EN-GB" -> "EN"
+fn base_lang(code: &str) -> &str {
+    code.split('-').next().unwrap_or(code)
+}
+
+// "Sign in" at the start of a sentence should give "Zaloguj się", not "zaloguj się"
+fn match_case(matched: &str, target: &str) -> String {
+    let starts_upper = matched.chars().next().is_some_and(char::is_uppercase);
+    let mut chars = target.chars();
+    match chars.next() {
+        Some(first) if starts_upper && first.is_lowercase() => {
+            first.to_uppercase().chain(chars).collect()
+        }
+        _ => target.to_string(),
+    }
+}
+
+impl Terminology {
+    pub fn empty() -> Self {
+        Self { pairs: Vec::new() }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        let file: TerminologyFile = serde_json::from_str(&data)
+            .map_err(|e| anyhow::anyhow!("Invalid terminology file {}: {}", path.display(), e))?;
+
+        let mut pairs = Vec::new();
+        for pair in file.pairs {
+            let mut terms: Vec<TermConfig> = pair
+                .terms
+                .into_iter()
+                .filter(|t| !t.source.trim().is_empty())
+                .collect();
+            if terms.is_empty() {
+                continue;
+            }
+            // Alternation is leftmost-first, so longer terms must come first to win
+            terms.sort_by_key(|t| std::cmp::Reverse(t.source.chars().count()));
+
+            let alternatives: Vec<String> = terms
+                .iter()
+                .map(|t| {
+                    let source = t.source.trim();
+                    // Word boundaries only where the term itself starts/ends with a word character
+                    let starts_word = source.chars().next().is_some_and(char::is_alphanumeric);
+                    let ends_word = source.chars().last().is_some_and(char::is_alphanumeric);
+                    format!(
+                        "{}{}{}",
+                        if starts_word { r"\b" } else { "" },
+                        regex::escape(source),
+                        if ends_word { r"\b" } else { "" }
+                    )
+                })
+                .collect();
+            let pattern = RegexBuilder::new(&alternatives.join("|"))
+                .case_insensitive(true)
+                .build()?;
+
+            pairs.push(TermPair {
+                source_lang: pair.source_lang.to_uppercase(),
+                target_lang: pair.target_lang.to_uppercase(),
+                pattern,
+                targets: terms
+                    .into_iter()
+                    .map(|t| (t.source.trim().to_lowercase(), t.target))
+                    .collect(),
+            });
+        }
+
+        Ok(Self { pairs })
+    }
+
+    pub fn pair_count(&self) -> usize {
+        self.pairs.len()
+    }
+
+    // Target regions share the base language's terms. Without a source language
+    // every pair into the target applies.
+    fn pairs_for<'a>(
+        &'a self,
+        options: &'a TranslateOptions,
+    ) -> impl Iterator<Item = &'a TermPair> {
+        let target = base_lang(&options.target_lang).to_uppercase();
+        self.pairs.iter().filter(move |pair| {
+            base_lang(&pair.target_lang) == target
+                && options
+                    .source_lang
+                    .as_deref()
+                    .is_none_or(|source| pair.source_lang.eq_ignore_ascii_case(source))
+        })
+    }
+
+    /// Replaces known source terms with placeholders. Upstream glossaries take
+    /// precedence, so nothing is masked when the request names one.
+    pub fn mask(&self, text: &str, options: &TranslateOptions) -> MaskedText {
+        let mut masked = MaskedText {
+            text: text.to_string(),
+            terms: Vec::new(),
+        };
+        if options.glossary_id.is_some() {
+            return masked;
+        }
+
+        for pair in self.pairs_for(options) {
+            let terms = &mut masked.terms;
+            masked.text = pair
+                .pattern
+                .replace_all(&masked.text, |caps: &Captures| {
+                    let matched = &caps[0];
+                    let target = pair
+                        .targets
+                        .get(&matched.to_lowercase())
+                        .map(|target| match_case(matched, target))
+                        .unwrap_or_else(|| matched.to_string());
+                    terms.push(AppliedTerm {
+                        source: matched.to_string(),
+                        target,
+                    });
+                    placeholder(terms.len() - 1)
+                })
+                .into_owned();
+        }
+        masked
+    }
+}
+
+impl MaskedText {
+    /// Puts the target terms in place of the placeholders in `translated`.
+    /// Returns the text and the distinct terms that made it through.
+    pub fn restore(&self, translated: &str) -> (String, Vec<AppliedTerm>) {
+        if self.terms.is_empty() {
+            return (translated.to_string(), Vec::new());
+        }
+
+        let mut restored = vec![false; self.terms.len()];
+        let text = placeholder_pattern()
+            .replace_all(translated, |caps: &Captures| {
+                let term = caps[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| Some((index, self.terms.get(index)?)));
+                match term {
+                    Some((index, term)) => {
+                        restored[index] = true;
+                        term.target.clone()
+                    }
+                    None => caps[0].to_string(),
+                }
+            })
+            .into_owned();
+
+        let mut applied: Vec<AppliedTerm> = Vec::new();
+        for (term, restored) in self.terms.iter().zip(restored) {
+            if !restored {
+                warn!("Term '{}' was lost in translation", term.source);
+            } else if !applied.contains(term) {
+                applied.push(term.clone());
+            }
+        }
+        (text, applied)
+    }
+}
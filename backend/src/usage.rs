@@ -0,0 +1,214 @@
+use crate::auth::AuthUser;
+use crate::error::AppError;
+use crate::provider::ProviderError;
+use chrono::Utc;
+use common::{ApiError, UsagePeriod, UsageReport};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Character limits for one user. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quota {
+    pub daily: Option<u64>,
+    pub monthly: Option<u64>,
+}
+
+/// Characters billed to one user in the current day and month (UTC)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct UserUsage {
+    day: String,
+    day_chars: u64,
+    month: String,
+    month_chars: u64,
+}
+
+impl UserUsage {
+    // Resets counters whose period has ended
+    fn roll_over(&mut self, day: &str, month: &str) {
+        if self.day != day {
+            self.day = day.to_string();
+            self.day_chars = 0;
+        }
+        if self.month != month {
+            self.month = month.to_string();
+            self.month_chars = 0;
+        }
+    }
+}
+
+fn current_periods() -> (String, String) {
+    let now = Utc::now();
+    (
+        now.format("%Y-%m-%d").to_string(),
+        now.format("%Y-%m").to_string(),
+    )
+}
+
+/// DeepL bills every character of the source text, whitespace included
+pub fn billed_chars<'a>(texts: impl IntoIterator<Item = &'a String>) -> u64 {
+    texts.into_iter().map(|t| t.chars().count() as u64).sum()
+}
+
+/// An upstream failure after `billed` of the characters sent were already
+/// billed, such as a batch whose later chunk failed
+pub struct PartialFailure {
+    pub error: ProviderError,
+    pub billed: u64,
+}
+
+impl From<ProviderError> for PartialFailure {
+    fn from(error: ProviderError) -> Self {
+        Self { error, billed: 0 }
+    }
+}
+
+/// Counts billed characters per user and enforces their quotas
+pub struct UsageTracker {
+    users: Mutex<HashMap<String, UserUsage>>,
+    persist_path: Option<PathBuf>,
+}
+
+impl UsageTracker {
+    pub fn new(persist_path: Option<PathBuf>) -> Self {
+        Self {
+            users: Mutex::new(HashMap::new()),
+            persist_path,
+        }
+    }
+
+    /// Books `chars` against the user's quota, failing if that would exceed it
+    fn reserve(&self, user: &AuthUser, chars: u64) -> Result<(), ApiError> {
+        let (day, month) = current_periods();
+        let mut users = self.users.lock().unwrap();
+        let usage = users.entry(user.name.clone()).or_default();
+        usage.roll_over(&day, &month);
+
+        if let Some(limit) = user.quota.daily {
+            if usage.day_chars + chars > limit {
+                return Err(ApiError::UserQuotaExceeded(format!(
+                    "Daily limit of {} characters would be exceeded ({} used, {} requested)",
+                    limit, usage.day_chars, chars
+                )));
+            }
+        }
+        if let Some(limit) = user.quota.monthly {
+            if usage.month_chars + chars > limit {
+                return Err(ApiError::UserQuotaExceeded(format!(
+                    "Monthly limit of {} characters would be exceeded ({} used, {} requested)",
+                    limit, usage.month_chars, chars
+                )));
+            }
+        }
+
+        usage.day_chars += chars;
+        usage.month_chars += chars;
+        Ok(())
+    }
+
+    // Gives back a reservation for a call that was not billed upstream
+    fn release(&self, user: &AuthUser, chars: u64) {
+        let (day, month) = current_periods();
+        let mut users = self.users.lock().unwrap();
+        if let Some(usage) = users.get_mut(&user.name) {
+            if usage.day == day {
+                usage.day_chars = usage.day_chars.saturating_sub(chars);
+            }
+            if usage.month == month {
+                usage.month_chars = usage.month_chars.saturating_sub(chars);
+            }
+        }
+    }
+
+    /// Runs an upstream call billed at `chars` characters to `user`, rejecting
+    /// it up front if the user's quota does not allow it. When the call fails,
+    /// only the characters it had not been billed for yet are given back.
+    pub async fn metered<T, E: Into<PartialFailure>>(
+        &self,
+        user: &AuthUser,
+        chars: u64,
+        call: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, AppError> {
+        self.reserve(user, chars)?;
+        match call.await {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                let failure = e.into();
+                self.release(user, chars.saturating_sub(failure.billed));
+                Err(failure.error.into())
+            }
+        }
+    }
+
+    pub fn report(&self, user: &AuthUser) -> UsageReport {
+        let (day, month) = current_periods();
+        let mut users = self.users.lock().unwrap();
+        let usage = users.entry(user.name.clone()).or_default();
+        usage.roll_over(&day, &month);
+
+        UsageReport {
+            user: user.name.clone(),
+            daily: UsagePeriod {
+                period: day,
+                used: usage.day_chars,
+                limit: user.quota.daily,
+            },
+            monthly: UsagePeriod {
+                period: month,
+                used: usage.month_chars,
+                limit: user.quota.monthly,
+            },
+        }
+    }
+
+    /// Loads persisted counters. A missing file is not an error.
+    pub async fn load(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.persist_path else {
+            return Ok(());
+        };
+        let data = match tokio::fs::read(path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let persisted: HashMap<String, UserUsage> = serde_json::from_slice(&data)?;
+        info!(
+            "Loaded usage for {} users from {}",
+            persisted.len(),
+            path.display()
+        );
+        *self.users.lock().unwrap() = persisted;
+        Ok(())
+    }
+
+    pub async fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.persist_path else {
+            return Ok(());
+        };
+        let data = serde_json::to_vec_pretty(&*self.users.lock().unwrap())?;
+
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, data).await?;
+        tokio::fs::rename(&tmp_path, path).await?;
+        Ok(())
+    }
+
+    /// Persists the counters every `interval` until the process exits
+    pub async fn persist_periodically(&self, interval: Duration) {
+        if self.persist_path.is_none() {
+            return;
+        }
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.save().await {
+                warn!("Failed to persist usage counters: {}", e);
+            }
+        }
+    }
+}
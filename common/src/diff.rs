@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+/// One piece of a word-level diff between an original and an improved text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum DiffChunk {
+    /// Text present in both versions
+    Equal { text: String },
+    /// `removed` from the original was replaced by `added`; either may be empty
+    Change { removed: String, added: String },
+}
+
+/// Word-level diff of `original` against `improved`. Changes separated only by
+/// whitespace are merged so a rephrased clause shows up as a single change.
+pub fn word_diff(original: &str, improved: &str) -> Vec<DiffChunk> {
+    let diff = TextDiff::from_words(original, improved);
+    let mut chunks: Vec<DiffChunk> = Vec::new();
+
+    for change in diff.iter_all_changes() {
+        let text = change.value();
+        if change.tag() == ChangeTag::Equal {
+            match chunks.last_mut() {
+                Some(DiffChunk::Equal { text: last }) => last.push_str(text),
+                _ => chunks.push(DiffChunk::Equal {
+                    text: text.to_string(),
+                }),
+            }
+            continue;
+        }
+
+        let after_whitespace_gap = matches!(
+            chunks.as_slice(),
+            [.., DiffChunk::Change { .. }, DiffChunk::Equal { text }] if text.trim().is_empty()
+        );
+        if after_whitespace_gap {
+            if let Some(DiffChunk::Equal { text: gap }) = chunks.pop() {
+                if let Some(DiffChunk::Change { removed, added }) = chunks.last_mut() {
+                    removed.push_str(&gap);
+                    added.push_str(&gap);
+                }
+            }
+        } else if !matches!(chunks.last(), Some(DiffChunk::Change { .. })) {
+            chunks.push(DiffChunk::Change {
+                removed: String::new(),
+                added: String::new(),
+            });
+        }
+
+        if let Some(DiffChunk::Change { removed, added }) = chunks.last_mut() {
+            if change.tag() == ChangeTag::Delete {
+                removed.push_str(text);
+            } else {
+                added.push_str(text);
+            }
+        }
+    }
+
+    chunks
+}
+
+/// Rebuilds the text, keeping `added` for changes where `accept(index)` is true
+/// and `removed` otherwise. `index` is the chunk's position in `chunks`.
+pub fn apply_changes(chunks: &[DiffChunk], accept: impl Fn(usize) -> bool) -> String {
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| match chunk {
+            DiffChunk::Equal { text } => text.as_str(),
+            DiffChunk::Change { added, .. } if accept(index) => added.as_str(),
+            DiffChunk::Change { removed, .. } => removed.as_str(),
+        })
+        .collect()
+}
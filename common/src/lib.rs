@@ -1,16 +1,185 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+mod diff;
+
+pub use diff::{apply_changes, word_diff, DiffChunk};
+
+/// Why a request failed. Serialized as `{"kind": "...", "message": "..."}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ApiError {
+    /// The upstream account has used up its character allowance
+    QuotaExceeded(String),
+    /// The upstream rejected the proxy's credentials
+    AuthFailure(String),
+    /// The upstream is throttling requests; retry later
+    RateLimited(String),
+    /// The request itself is invalid and retrying will not help
+    BadInput(String),
+    /// The upstream could not be reached or is failing
+    UpstreamUnavailable(String),
+    /// The upstream answered with something we could not understand
+    ParseFailure(String),
+    /// The configured provider does not offer this operation
+    Unsupported(String),
+    /// The caller did not present valid credentials
+    Unauthorized(String),
+    /// The caller is authenticated but may not use this endpoint
+    Forbidden(String),
+    /// The caller used up their own character quota on this proxy
+    UserQuotaExceeded(String),
+    /// The requested resource (e.g. a glossary) does not exist upstream
+    NotFound(String),
+    /// The upstream's output lost or broke something the input had, such as
+    /// markup tags, so it was not returned
+    OutputCheckFailed(String),
+}
+
+impl ApiError {
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::QuotaExceeded(message)
+            | ApiError::AuthFailure(message)
+            | ApiError::RateLimited(message)
+            | ApiError::BadInput(message)
+            | ApiError::UpstreamUnavailable(message)
+            | ApiError::ParseFailure(message)
+            | ApiError::Unsupported(message)
+            | ApiError::Unauthorized(message)
+            | ApiError::Forbidden(message)
+            | ApiError::UserQuotaExceeded(message)
+            | ApiError::NotFound(message)
+            | ApiError::OutputCheckFailed(message) => message,
+        }
+    }
+
+    /// Short human-readable label for the error category
+    pub fn title(&self) -> &'static str {
+        match self {
+            ApiError::QuotaExceeded(_) => "Quota exceeded",
+            ApiError::AuthFailure(_) => "Authentication failed",
+            ApiError::RateLimited(_) => "Too many requests",
+            ApiError::BadInput(_) => "Invalid request",
+            ApiError::UpstreamUnavailable(_) => "Service unavailable",
+            ApiError::ParseFailure(_) => "Unexpected response",
+            ApiError::Unsupported(_) => "Not supported",
+            ApiError::Unauthorized(_) => "Not signed in",
+            ApiError::Forbidden(_) => "Access denied",
+            ApiError::UserQuotaExceeded(_) => "Your character quota is used up",
+            ApiError::NotFound(_) => "Not found",
+            ApiError::OutputCheckFailed(_) => "Translation check failed",
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.title(), self.message())
+    }
+}
+
+impl std::error::Error for ApiError {}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranslateRequest {
     pub text: String,
+    /// Language of `text`; detected by the upstream when omitted
+    pub source_lang: Option<String>,
     pub target_lang: Option<String>,
+    /// One of [`FORMALITIES`]. `more`/`less` require a target language with
+    /// `supports_formality`; `prefer_*` is ignored for the others.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formality: Option<String>,
+    /// Glossary from `/glossaries` to apply. Requires `source_lang`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossary_id: Option<String>,
+    #[serde(flatten)]
+    pub tags: TagOptions,
+    /// Keeps format-string placeholders (`{name}`, `%s`, `{{name}}`, ICU
+    /// plurals, ...) away from the upstream and fails the translation if one
+    /// goes missing or is duplicated
+    #[serde(default)]
+    pub protect_placeholders: bool,
+    #[serde(default)]
+    pub format: TextFormat,
+}
+
+/// How the text of a [`TranslateRequest`] is parsed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextFormat {
+    #[default]
+    Plain,
+    /// Only prose is translated; code, link targets, inline HTML and front
+    /// matter are kept as written
+    Markdown,
+}
+
+/// How markup in the text is treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagHandling {
+    Html,
+    Xml,
+}
+
+/// Markup options, serialized inline with the request. Without `tag_handling`
+/// tags are translated as plain text and none of the other fields apply.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_handling: Option<TagHandling>,
+    /// Elements whose content is left untranslated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_tags: Vec<String>,
+    /// Elements that never split a sentence, e.g. inline formatting (XML only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub non_splitting_tags: Vec<String>,
+    /// Elements that always start a new sentence (XML only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub splitting_tags: Vec<String>,
+    /// Set to false to rely only on `splitting_tags` for sentence structure (XML only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outline_detection: Option<bool>,
 }
 
+/// Formality levels accepted by DeepL
+pub const FORMALITIES: &[&str] = &["more", "less", "prefer_more", "prefer_less"];
+
+/// Writing styles accepted by DeepL Write. `prefer_*` falls back to the default
+/// style for languages without style support instead of failing.
+pub const WRITING_STYLES: &[&str] = &[
+    "simple",
+    "business",
+    "academic",
+    "casual",
+    "prefer_simple",
+    "prefer_business",
+    "prefer_academic",
+    "prefer_casual",
+];
+
+/// Tones accepted by DeepL Write, with the same `prefer_*` fallback
+pub const TONES: &[&str] = &[
+    "enthusiastic",
+    "friendly",
+    "confident",
+    "diplomatic",
+    "prefer_enthusiastic",
+    "prefer_friendly",
+    "prefer_confident",
+    "prefer_diplomatic",
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImproveRequest {
     pub text: String,
     pub target_lang: Option<String>,
+    /// One of [`WRITING_STYLES`]; cannot be combined with `tone`
     pub writing_style: Option<String>,
+    /// One of [`TONES`]; cannot be combined with `writing_style`
     pub tone: Option<String>,
 }
 
@@ -18,5 +187,407 @@ pub struct ImproveRequest {
 pub struct ApiResponse {
     pub result: String,
     pub success: bool,
-    pub error: Option<String>,
-} 
\ No newline at end of file
+    pub error: Option<ApiError>,
+    /// Source language reported by the upstream, if it detected one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_source_language: Option<String>,
+    /// Word-level changes from the submitted text to `result`, set by `/improve`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<Vec<DiffChunk>>,
+    /// Local terminology substituted into `result`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub applied_terms: Vec<AppliedTerm>,
+}
+
+/// A term from the proxy's local terminology that was enforced in a translation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedTerm {
+    /// The term as it appeared in the submitted text
+    pub source: String,
+    /// What it was translated to
+    pub target: String,
+}
+
+/// Who the proxy thinks the caller is, returned by `/auth/me`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthInfo {
+    pub user: String,
+    pub admin: bool,
+    /// Whether the proxy rejects requests without a token
+    pub auth_required: bool,
+}
+
+/// Characters billed to a user in one quota period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsagePeriod {
+    /// `YYYY-MM-DD` for days, `YYYY-MM` for months (UTC)
+    pub period: String,
+    pub used: u64,
+    /// `None` when the user has no limit for this period
+    pub limit: Option<u64>,
+}
+
+/// Returned by `/usage` for the authenticated caller
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub user: String,
+    pub daily: UsagePeriod,
+    pub monthly: UsagePeriod,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Language {
+    /// Code to send as `source_lang` / `target_lang`, e.g. "DE" or "EN-GB"
+    pub code: String,
+    pub name: String,
+    /// Whether `formality` may be set when translating into this language
+    #[serde(default)]
+    pub supports_formality: bool,
+}
+
+/// Returned by `/languages`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguagesResponse {
+    pub source: Vec<Language>,
+    pub target: Vec<Language>,
+}
+
+/// Usage of the upstream account (e.g. DeepL) in its current billing period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderUsage {
+    pub provider: String,
+    pub character_count: u64,
+    pub character_limit: u64,
+}
+
+impl ProviderUsage {
+    /// Share of the allowance used, from 0.0 to 1.0 (or above if overdrawn)
+    pub fn fraction_used(&self) -> f64 {
+        if self.character_limit == 0 {
+            return 1.0;
+        }
+        self.character_count as f64 / self.character_limit as f64
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchTranslateItem {
+    pub id: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchTranslateRequest {
+    pub items: Vec<BatchTranslateItem>,
+    pub source_lang: Option<String>,
+    pub target_lang: Option<String>,
+    /// Applied to every item, see [`TranslateRequest::formality`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formality: Option<String>,
+    /// Applied to every item, see [`TranslateRequest::glossary_id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossary_id: Option<String>,
+    /// Applied to every item
+    #[serde(flatten)]
+    pub tags: TagOptions,
+    /// Applied to every item, see [`TranslateRequest::protect_placeholders`]
+    #[serde(default)]
+    pub protect_placeholders: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchTranslateResult {
+    pub id: String,
+    pub result: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_source_language: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub applied_terms: Vec<AppliedTerm>,
+    /// Set when this item failed on its own; `result` is then empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ApiError>,
+}
+
+/// Results are returned in the same order as the request items
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchTranslateResponse {
+    // Absent when the whole batch failed
+    #[serde(default)]
+    pub results: Vec<BatchTranslateResult>,
+    pub success: bool,
+    pub error: Option<ApiError>,
+}
+
+/// A glossary stored with the upstream provider. Entries cannot be edited;
+/// delete the glossary and create a new one instead.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GlossaryInfo {
+    pub glossary_id: String,
+    pub name: String,
+    /// False while the upstream is still processing a new glossary
+    pub ready: bool,
+    pub source_lang: String,
+    pub target_lang: String,
+    /// ISO 8601 timestamp
+    pub creation_time: String,
+    pub entry_count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlossaryList {
+    pub glossaries: Vec<GlossaryInfo>,
+}
+
+/// How `CreateGlossaryRequest::entries` is encoded, one `source<sep>target` pair per line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GlossaryFormat {
+    #[default]
+    Tsv,
+    Csv,
+}
+
+impl GlossaryFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GlossaryFormat::Tsv => "tsv",
+            GlossaryFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateGlossaryRequest {
+    pub name: String,
+    pub source_lang: String,
+    pub target_lang: String,
+    /// Contents of the uploaded TSV or CSV file
+    pub entries: String,
+    #[serde(default)]
+    pub entries_format: GlossaryFormat,
+}
+
+/// Subtitle file formats accepted by `/translate/subtitles`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleFormat {
+    /// SubRip
+    Srt,
+    /// WebVTT
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "application/x-subrip",
+            SubtitleFormat::Vtt => "text/vtt",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubtitleTranslateRequest {
+    /// Contents of the uploaded .srt or .vtt file
+    pub content: String,
+    /// Detected from `content` when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<SubtitleFormat>,
+    pub source_lang: Option<String>,
+    pub target_lang: Option<String>,
+    /// See [`TranslateRequest::formality`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formality: Option<String>,
+    /// See [`TranslateRequest::glossary_id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossary_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubtitleTranslateResponse {
+    /// The translated file, in the same format as the request
+    pub content: String,
+    pub format: SubtitleFormat,
+    /// Number of cues in the file
+    pub cues: usize,
+    /// 1-based numbers of the cues left in the source language because their
+    /// styling tags did not survive translation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub untranslated_cues: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_source_language: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoTranslateRequest {
+    /// Contents of the uploaded .po or .pot file
+    pub content: String,
+    pub source_lang: Option<String>,
+    pub target_lang: Option<String>,
+    /// See [`TranslateRequest::formality`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formality: Option<String>,
+    /// See [`TranslateRequest::glossary_id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossary_id: Option<String>,
+    /// Also retranslate entries marked fuzzy, not only untranslated ones
+    #[serde(default)]
+    pub include_fuzzy: bool,
+}
+
+/// What tells the entries of a gettext catalog apart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoMessageId {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msgctxt: Option<String>,
+    pub msgid: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoTranslateResponse {
+    /// The updated catalog
+    pub content: String,
+    /// Number of entries given a machine translation, all marked fuzzy
+    pub translated: usize,
+    /// Entries left as they were because their placeholders did not survive
+    /// translation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<PoMessageId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_source_language: Option<String>,
+}
+
+/// Locale file formats accepted by `/translate/resources`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceFormat {
+    Json,
+    Yaml,
+}
+
+impl ResourceFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ResourceFormat::Json => "json",
+            ResourceFormat::Yaml => "yaml",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ResourceFormat::Json => "application/json",
+            ResourceFormat::Yaml => "application/yaml",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceTranslateRequest {
+    /// Contents of the source locale file
+    pub content: String,
+    /// Detected from `content` when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<ResourceFormat>,
+    pub source_lang: Option<String>,
+    /// One translated file is returned per language
+    pub target_langs: Vec<String>,
+    /// See [`TranslateRequest::formality`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formality: Option<String>,
+    /// See [`TranslateRequest::glossary_id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossary_id: Option<String>,
+    /// Current locale file of some target languages, by language code. Only
+    /// the keys missing from it are translated for that language.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub existing: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslatedResource {
+    pub target_lang: String,
+    /// The locale file for `target_lang`
+    pub content: String,
+    /// Number of strings given a machine translation
+    pub translated: usize,
+    /// Keys left out because their placeholders did not survive translation,
+    /// dot-separated ("home.title")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceTranslateResponse {
+    pub format: ResourceFormat,
+    /// In the order of `target_langs`
+    pub files: Vec<TranslatedResource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_source_language: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct XliffTranslateRequest {
+    /// Contents of the XLIFF 1.2 or 2.x file
+    pub content: String,
+    /// Taken from the file when omitted
+    pub source_lang: Option<String>,
+    /// Taken from the file when omitted
+    pub target_lang: Option<String>,
+    /// See [`TranslateRequest::formality`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formality: Option<String>,
+    /// See [`TranslateRequest::glossary_id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossary_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct XliffTranslateResponse {
+    /// The file with the pretranslated targets
+    pub content: String,
+    /// XLIFF version of the file, e.g. "1.2"
+    pub version: String,
+    /// Number of units or segments given a machine translation
+    pub translated: usize,
+    /// Ids of the units left untranslated because their inline elements did
+    /// not survive translation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_source_language: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FluentTranslateRequest {
+    /// Contents of the .ftl file
+    pub content: String,
+    pub source_lang: Option<String>,
+    pub target_lang: Option<String>,
+    /// See [`TranslateRequest::formality`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formality: Option<String>,
+    /// See [`TranslateRequest::glossary_id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossary_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FluentTranslateResponse {
+    /// The translated resource
+    pub content: String,
+    /// Number of messages rewritten with a translation
+    pub translated: usize,
+    /// Ids of the messages kept in the source language because their
+    /// translation lost placeables or no longer parsed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_source_language: Option<String>,
+}
@@ -0,0 +1,119 @@
+use common::{ApiError, ApiResponse};
+use gloo_net::http::{Request, RequestBuilder, Response};
+use gloo_storage::{LocalStorage, Storage};
+use serde::{de::DeserializeOwned, Serialize};
+
+const TOKEN_STORAGE_KEY: &str = "deepl-proxy.token";
+
+// Helper function to get the backend URL based on current location
+pub fn get_backend_url() -> String {
+    let window = web_sys::window().expect("no global `window` exists");
+    let location = window.location();
+    let protocol = location.protocol().unwrap_or_else(|_| "http:".to_string());
+    let host = location
+        .host()
+        .unwrap_or_else(|_| "localhost:3000".to_string());
+    format!("{}//{}", protocol, host)
+}
+
+pub fn stored_token() -> Option<String> {
+    LocalStorage::get(TOKEN_STORAGE_KEY).ok()
+}
+
+pub fn store_token(token: &str) {
+    // Storage can be unavailable (e.g. private mode); the session then simply
+    // won't survive a reload
+    let _ = LocalStorage::set(TOKEN_STORAGE_KEY, token);
+}
+
+pub fn clear_token() {
+    LocalStorage::delete(TOKEN_STORAGE_KEY);
+}
+
+fn with_auth(builder: RequestBuilder) -> RequestBuilder {
+    match stored_token() {
+        Some(token) => builder.header("Authorization", &format!("Bearer {}", token)),
+        None => builder,
+    }
+}
+
+// Non-2xx responses carry an `ApiResponse` with the `ApiError` that caused them
+async fn check_status(response: Response) -> Result<Response, ApiError> {
+    if !response.ok() {
+        let api_response = response
+            .json::<ApiResponse>()
+            .await
+            .map_err(|e| ApiError::ParseFailure(format!("Parse error: {}", e)))?;
+        return Err(api_response.error.unwrap_or_else(|| {
+            ApiError::UpstreamUnavailable(format!("HTTP {}", response.status()))
+        }));
+    }
+    Ok(response)
+}
+
+async fn read_json<R: DeserializeOwned>(response: Response) -> Result<R, ApiError> {
+    check_status(response)
+        .await?
+        .json::<R>()
+        .await
+        .map_err(|e| ApiError::ParseFailure(format!("Parse error: {}", e)))
+}
+
+fn request_error(e: gloo_net::Error) -> ApiError {
+    ApiError::UpstreamUnavailable(format!("Request error: {}", e))
+}
+
+pub async fn get_json<R: DeserializeOwned>(path: &str) -> Result<R, ApiError> {
+    let response = with_auth(Request::get(&format!("{}{}", get_backend_url(), path)))
+        .send()
+        .await
+        .map_err(request_error)?;
+    read_json(response).await
+}
+
+pub async fn get_text(path: &str) -> Result<String, ApiError> {
+    let response = with_auth(Request::get(&format!("{}{}", get_backend_url(), path)))
+        .send()
+        .await
+        .map_err(request_error)?;
+    check_status(response)
+        .await?
+        .text()
+        .await
+        .map_err(|e| ApiError::ParseFailure(format!("Parse error: {}", e)))
+}
+
+pub async fn delete(path: &str) -> Result<(), ApiError> {
+    let response = with_auth(Request::delete(&format!("{}{}", get_backend_url(), path)))
+        .send()
+        .await
+        .map_err(request_error)?;
+    check_status(response).await.map(|_| ())
+}
+
+pub async fn post_json<T: Serialize, R: DeserializeOwned>(
+    path: &str,
+    request: &T,
+) -> Result<R, ApiError> {
+    let response = with_auth(Request::post(&format!("{}{}", get_backend_url(), path)))
+        .json(request)
+        .map_err(|e| ApiError::BadInput(format!("Could not encode request: {}", e)))?
+        .send()
+        .await
+        .map_err(request_error)?;
+    read_json(response).await
+}
+
+// Posts `request` to the backend. Failures reported by the backend come back as
+// the `ApiError` it sent; transport and decoding failures are mapped onto one.
+pub async fn post_api<T: Serialize>(path: &str, request: &T) -> Result<ApiResponse, ApiError> {
+    let api_response: ApiResponse = post_json(path, request).await?;
+
+    if api_response.success {
+        Ok(api_response)
+    } else {
+        Err(api_response
+            .error
+            .unwrap_or_else(|| ApiError::UpstreamUnavailable("Unknown error".to_string())))
+    }
+}
@@ -0,0 +1,84 @@
+use common::{apply_changes, DiffChunk};
+use std::collections::HashSet;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct DiffViewProps {
+    pub chunks: Vec<DiffChunk>,
+}
+
+/// Highlights what `/improve` changed. Clicking a change rejects or re-accepts it;
+/// the text below reflects the current choices.
+#[function_component(DiffView)]
+pub fn diff_view(props: &DiffViewProps) -> Html {
+    // Indices of rejected changes; everything is accepted initially
+    let rejected = use_state(HashSet::<usize>::new);
+
+    let change_count = props
+        .chunks
+        .iter()
+        .filter(|chunk| matches!(chunk, DiffChunk::Change { .. }))
+        .count();
+    let merged = apply_changes(&props.chunks, |index| !rejected.contains(&index));
+
+    let on_accept_all = {
+        let rejected = rejected.clone();
+        Callback::from(move |_| rejected.set(HashSet::new()))
+    };
+
+    let on_reject_all = {
+        let rejected = rejected.clone();
+        let changes: HashSet<usize> = props
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| matches!(chunk, DiffChunk::Change { .. }))
+            .map(|(index, _)| index)
+            .collect();
+        Callback::from(move |_| rejected.set(changes.clone()))
+    };
+
+    let render_chunk = |(index, chunk): (usize, &DiffChunk)| match chunk {
+        DiffChunk::Equal { text } => html! { <span>{text.clone()}</span> },
+        DiffChunk::Change { removed, added } => {
+            let is_rejected = rejected.contains(&index);
+            let on_toggle = {
+                let rejected = rejected.clone();
+                Callback::from(move |_| {
+                    let mut updated = (*rejected).clone();
+                    if !updated.remove(&index) {
+                        updated.insert(index);
+                    }
+                    rejected.set(updated);
+                })
+            };
+            html! {
+                <span
+                    class={classes!("diff-change", is_rejected.then_some("diff-rejected"))}
+                    title={if is_rejected { "Rejected - click to accept" } else { "Accepted - click to reject" }}
+                    onclick={on_toggle}
+                >
+                    {if removed.is_empty() { html! {} } else { html! { <del>{removed.clone()}</del> } }}
+                    {if added.is_empty() { html! {} } else { html! { <ins>{added.clone()}</ins> } }}
+                </span>
+            }
+        }
+    };
+
+    html! {
+        <div class="result">
+            <h4 class="result-title">
+                {format!("Changes ({} of {} accepted):", change_count - rejected.len(), change_count)}
+            </h4>
+            <p class="result-text diff">
+                {for props.chunks.iter().enumerate().map(render_chunk)}
+            </p>
+            <div class="diff-actions">
+                <button class="btn btn-secondary btn-small" onclick={on_accept_all}>{"Accept all"}</button>
+                <button class="btn btn-secondary btn-small" onclick={on_reject_all}>{"Reject all"}</button>
+            </div>
+            <h4 class="result-title">{"Improved Text:"}</h4>
+            <p class="result-text">{merged}</p>
+        </div>
+    }
+}
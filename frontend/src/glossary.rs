@@ -0,0 +1,391 @@
+use crate::api::{delete, get_json, get_text, post_json};
+use crate::panel::{language_name, use_languages};
+use crate::render_error;
+use common::{ApiError, CreateGlossaryRequest, GlossaryFormat, GlossaryInfo, GlossaryList};
+use gloo_file::{Blob, ObjectUrl};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct GlossaryManagerProps {
+    /// Only admins may create and delete glossaries
+    pub admin: bool,
+    /// Called after a glossary was created or deleted
+    pub on_change: Callback<()>,
+}
+
+// Entries of the glossary being viewed, with a URL to download them from
+struct EntriesView {
+    glossary: GlossaryInfo,
+    entries: Result<(String, ObjectUrl), ApiError>,
+}
+
+/// Lists the upstream glossaries and lets admins create new ones from TSV/CSV files
+#[function_component(GlossaryManager)]
+pub fn glossary_manager(props: &GlossaryManagerProps) -> Html {
+    let languages = use_languages();
+    let glossaries = use_state(|| None::<Result<Vec<GlossaryInfo>, ApiError>>);
+    let reload = use_state(|| 0u32);
+
+    let name = use_state(String::new);
+    let source = use_state(|| "EN".to_string());
+    let target = use_state(|| "PL".to_string());
+    let format = use_state(GlossaryFormat::default);
+    let entries = use_state(String::new);
+    let creating = use_state(|| false);
+    let create_result = use_state(|| None::<Result<GlossaryInfo, ApiError>>);
+
+    let viewing = use_state(|| None::<EntriesView>);
+    let delete_error = use_state(|| None::<ApiError>);
+
+    {
+        let glossaries = glossaries.clone();
+        use_effect_with(*reload, move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                let list = get_json::<GlossaryList>("/glossaries").await;
+                glossaries.set(Some(list.map(|list| list.glossaries)));
+            });
+            || ()
+        });
+    }
+
+    // Glossaries are defined on base languages, so "EN-GB" and "EN-US" collapse into "EN"
+    let mut target_bases: Vec<String> = Vec::new();
+    for language in &languages.target {
+        let base = language
+            .code
+            .split('-')
+            .next()
+            .unwrap_or(&language.code)
+            .to_string();
+        if !target_bases.contains(&base) {
+            target_bases.push(base);
+        }
+    }
+
+    let on_name_input = {
+        let name = name.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(input) = e.target_dyn_into::<web_sys::HtmlInputElement>() {
+                name.set(input.value());
+            }
+        })
+    };
+
+    let on_source_change = {
+        let source = source.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                source.set(select.value());
+            }
+        })
+    };
+
+    let on_target_change = {
+        let target = target.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                target.set(select.value());
+            }
+        })
+    };
+
+    let on_format_change = {
+        let format = format.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                format.set(if select.value() == "csv" {
+                    GlossaryFormat::Csv
+                } else {
+                    GlossaryFormat::Tsv
+                });
+            }
+        })
+    };
+
+    let on_entries_input = {
+        let entries = entries.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(input) = e.target_dyn_into::<web_sys::HtmlTextAreaElement>() {
+                entries.set(input.value());
+            }
+        })
+    };
+
+    // Loads the chosen file into the entries textarea so it can be checked first
+    let on_file_change = {
+        let name = name.clone();
+        let format = format.clone();
+        let entries = entries.clone();
+        let create_result = create_result.clone();
+        Callback::from(move |e: Event| {
+            let Some(input) = e.target_dyn_into::<web_sys::HtmlInputElement>() else {
+                return;
+            };
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            let file = gloo_file::File::from(file);
+            let file_name = file.name();
+
+            if file_name.to_lowercase().ends_with(".csv") {
+                format.set(GlossaryFormat::Csv);
+            } else {
+                format.set(GlossaryFormat::Tsv);
+            }
+            if name.is_empty() {
+                let stem = file_name
+                    .rsplit_once('.')
+                    .map_or(file_name.as_str(), |(stem, _)| stem);
+                name.set(stem.to_string());
+            }
+
+            let entries = entries.clone();
+            let create_result = create_result.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match gloo_file::futures::read_as_text(&file).await {
+                    Ok(text) => entries.set(text),
+                    Err(e) => create_result.set(Some(Err(ApiError::BadInput(format!(
+                        "Could not read {}: {}",
+                        file_name, e
+                    ))))),
+                }
+            });
+        })
+    };
+
+    let on_create = {
+        let name = name.clone();
+        let source = source.clone();
+        let target = target.clone();
+        let format = format.clone();
+        let entries = entries.clone();
+        let creating = creating.clone();
+        let create_result = create_result.clone();
+        let reload = reload.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let request = CreateGlossaryRequest {
+                name: name.trim().to_string(),
+                source_lang: (*source).clone(),
+                target_lang: (*target).clone(),
+                entries: (*entries).clone(),
+                entries_format: *format,
+            };
+
+            let name = name.clone();
+            let entries = entries.clone();
+            let creating = creating.clone();
+            let create_result = create_result.clone();
+            let reload = reload.clone();
+            let on_change = on_change.clone();
+            creating.set(true);
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = post_json::<_, GlossaryInfo>("/glossaries", &request).await;
+                if result.is_ok() {
+                    name.set(String::new());
+                    entries.set(String::new());
+                    reload.set(*reload + 1);
+                    on_change.emit(());
+                }
+                create_result.set(Some(result));
+                creating.set(false);
+            });
+        })
+    };
+
+    let on_view = {
+        let viewing = viewing.clone();
+        Callback::from(move |glossary: GlossaryInfo| {
+            let viewing = viewing.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let path = format!("/glossaries/{}/entries", glossary.glossary_id);
+                let entries = get_text(&path).await.map(|text| {
+                    let blob =
+                        Blob::new_with_options(text.as_str(), Some("text/tab-separated-values"));
+                    (text, ObjectUrl::from(blob))
+                });
+                viewing.set(Some(EntriesView { glossary, entries }));
+            });
+        })
+    };
+
+    let on_delete = {
+        let viewing = viewing.clone();
+        let delete_error = delete_error.clone();
+        let reload = reload.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |glossary: GlossaryInfo| {
+            let message = format!(
+                "Delete glossary \"{}\"? Translations using it will stop working.",
+                glossary.name
+            );
+            if !gloo_dialogs::confirm(&message) {
+                return;
+            }
+
+            let viewing = viewing.clone();
+            let delete_error = delete_error.clone();
+            let reload = reload.clone();
+            let on_change = on_change.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let path = format!("/glossaries/{}", glossary.glossary_id);
+                match delete(&path).await {
+                    Ok(()) => {
+                        delete_error.set(None);
+                        if viewing
+                            .as_ref()
+                            .is_some_and(|v| v.glossary.glossary_id == glossary.glossary_id)
+                        {
+                            viewing.set(None);
+                        }
+                        reload.set(*reload + 1);
+                        on_change.emit(());
+                    }
+                    Err(error) => delete_error.set(Some(error)),
+                }
+            });
+        })
+    };
+
+    let render_row = |glossary: &GlossaryInfo| {
+        let on_view = {
+            let on_view = on_view.clone();
+            let glossary = glossary.clone();
+            Callback::from(move |_| on_view.emit(glossary.clone()))
+        };
+        let on_delete = {
+            let on_delete = on_delete.clone();
+            let glossary = glossary.clone();
+            Callback::from(move |_| on_delete.emit(glossary.clone()))
+        };
+        html! {
+            <tr>
+                <td>{&glossary.name}</td>
+                <td>{format!("{} → {}", glossary.source_lang, glossary.target_lang)}</td>
+                <td>{glossary.entry_count}</td>
+                <td>{if glossary.ready { "Ready" } else { "Processing" }}</td>
+                <td>{glossary.creation_time.split('T').next().unwrap_or(&glossary.creation_time)}</td>
+                <td class="glossary-actions">
+                    <button class="btn btn-secondary btn-small" onclick={on_view}>{"Entries"}</button>
+                    {if props.admin {
+                        html! {
+                            <button class="btn btn-secondary btn-small" onclick={on_delete}>{"Delete"}</button>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                </td>
+            </tr>
+        }
+    };
+
+    html! {
+        <div class="container">
+            <div class="section">
+                <h2 class="section-title">{"Glossaries"}</h2>
+                {match &*glossaries {
+                    None => html! { <p class="result-meta">{"Loading..."}</p> },
+                    Some(Err(error)) => render_error(error),
+                    Some(Ok(list)) if list.is_empty() => html! {
+                        <p class="result-meta">{"No glossaries yet."}</p>
+                    },
+                    Some(Ok(list)) => html! {
+                        <table class="glossary-table">
+                            <thead>
+                                <tr>
+                                    <th>{"Name"}</th>
+                                    <th>{"Languages"}</th>
+                                    <th>{"Entries"}</th>
+                                    <th>{"Status"}</th>
+                                    <th>{"Created"}</th>
+                                    <th></th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {for list.iter().map(render_row)}
+                            </tbody>
+                        </table>
+                    },
+                }}
+                {if let Some(error) = &*delete_error { render_error(error) } else { html! {} }}
+
+                {match &*viewing {
+                    Some(EntriesView { glossary, entries: Ok((text, url)) }) => html! {
+                        <div class="result">
+                            <h4 class="result-title">{format!("{} entries:", glossary.name)}</h4>
+                            <pre class="glossary-entries">{text.clone()}</pre>
+                            <a
+                                class="btn btn-secondary btn-small"
+                                href={url.to_string()}
+                                download={format!("{}.tsv", glossary.name)}
+                            >
+                                {"Download TSV"}
+                            </a>
+                        </div>
+                    },
+                    Some(EntriesView { entries: Err(error), .. }) => render_error(error),
+                    None => html! {},
+                }}
+            </div>
+
+            <form class="section" onsubmit={on_create} hidden={!props.admin}>
+                <h2 class="section-title">{"New Glossary"}</h2>
+                <input
+                    class="input"
+                    type="text"
+                    placeholder="Name"
+                    value={(*name).clone()}
+                    oninput={on_name_input}
+                />
+                <div class="panel-header">
+                    <select class="select" onchange={on_source_change}>
+                        {for languages.source.iter().map(|l| html! {
+                            <option value={l.code.clone()} selected={l.code == *source}>{&l.name}</option>
+                        })}
+                    </select>
+                    {"→"}
+                    <select class="select" onchange={on_target_change}>
+                        {for target_bases.iter().map(|code| html! {
+                            <option value={code.clone()} selected={*code == *target}>
+                                {language_name(&languages.source, code)}
+                            </option>
+                        })}
+                    </select>
+                    <select class="select" onchange={on_format_change}>
+                        <option value="tsv" selected={*format == GlossaryFormat::Tsv}>{"Tab-separated (TSV)"}</option>
+                        <option value="csv" selected={*format == GlossaryFormat::Csv}>{"Comma-separated (CSV)"}</option>
+                    </select>
+                    <input type="file" accept=".tsv,.csv,.txt" onchange={on_file_change} />
+                </div>
+                <textarea
+                    class="textarea"
+                    rows="8"
+                    placeholder="One entry per line: source term, then target term, separated by a tab (TSV) or comma (CSV)"
+                    value={(*entries).clone()}
+                    oninput={on_entries_input}
+                />
+                <button
+                    type="submit"
+                    class={classes!("btn", "btn-primary", creating.then_some("loading"))}
+                    disabled={*creating || name.trim().is_empty() || entries.trim().is_empty()}
+                >
+                    {if *creating { "Creating..." } else { "Create Glossary" }}
+                </button>
+
+                {match &*create_result {
+                    Some(Ok(glossary)) => html! {
+                        <div class="result">
+                            <h4 class="result-title">{"Glossary created"}</h4>
+                            <p class="result-text">
+                                {format!("{} ({} entries)", glossary.name, glossary.entry_count)}
+                            </p>
+                        </div>
+                    },
+                    Some(Err(error)) => render_error(error),
+                    None => html! {},
+                }}
+            </form>
+        </div>
+    }
+}
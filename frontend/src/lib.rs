@@ -1,15 +1,33 @@
-use common::{ApiResponse, ImproveRequest, TranslateRequest};
-use gloo_net::http::Request;
+use api::{clear_token, get_json, post_api};
+use common::{
+    ApiError, ApiResponse, AuthInfo, GlossaryInfo, GlossaryList, ImproveRequest, TONES,
+    WRITING_STYLES,
+};
+use diff::DiffView;
+use glossary::GlossaryManager;
+use login::Login;
+use panel::{load_panels, save_panels, use_languages, PanelConfig, TranslationPanel};
+use std::rc::Rc;
+use subtitles::SubtitleTranslator;
+use usage::UsageBar;
 use wasm_bindgen::prelude::*;
 use yew::prelude::*;
 
-// Helper function to get the backend URL based on current location
-fn get_backend_url() -> String {
-    let window = web_sys::window().expect("no global `window` exists");
-    let location = window.location();
-    let protocol = location.protocol().unwrap_or_else(|_| "http:".to_string());
-    let host = location.host().unwrap_or_else(|_| "localhost:3000".to_string());
-    format!("{}//{}",  protocol, host)
+mod api;
+mod diff;
+mod glossary;
+mod login;
+mod panel;
+mod subtitles;
+mod usage;
+
+pub(crate) fn render_error(error: &ApiError) -> Html {
+    html! {
+        <div class="result error">
+            <h4 class="result-title">{error.title()}</h4>
+            <p class="result-text">{error.message()}</p>
+        </div>
+    }
 }
 
 #[wasm_bindgen(start)]
@@ -17,40 +35,197 @@ pub fn run_app() {
     yew::Renderer::<App>::new().render();
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Page {
+    Translate,
+    Subtitles,
+    Glossaries,
+}
+
 #[function_component(App)]
 fn app() -> Html {
-    // EN -> PL translation states
-    let en_to_pl_text = use_state(|| String::new());
-    let en_to_pl_result = use_state(|| String::new());
-    let en_to_pl_loading = use_state(|| false);
+    let auth = use_state(|| None::<AuthInfo>);
+    let checking_auth = use_state(|| true);
+    // Bumped after every upstream request so the usage bar refreshes
+    let usage_refresh = use_state(|| 0u32);
+    let page = use_state(|| Page::Translate);
+    // Bumped when glossaries are created or deleted so the panels pick them up
+    let glossaries_version = use_state(|| 0u32);
+
+    // Find out whether a stored token is still valid, or whether one is needed at all
+    {
+        let auth = auth.clone();
+        let checking_auth = checking_auth.clone();
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                match get_json::<AuthInfo>("/auth/me").await {
+                    Ok(info) => auth.set(Some(info)),
+                    Err(ApiError::Unauthorized(_)) => clear_token(),
+                    Err(_) => {}
+                }
+                checking_auth.set(false);
+            });
+            || ()
+        });
+    }
+
+    let on_login = {
+        let auth = auth.clone();
+        Callback::from(move |info: AuthInfo| auth.set(Some(info)))
+    };
+
+    let on_logout = {
+        let auth = auth.clone();
+        Callback::from(move |_| {
+            clear_token();
+            auth.set(None);
+        })
+    };
+
+    let on_request_done = {
+        let usage_refresh = usage_refresh.clone();
+        Callback::from(move |_| usage_refresh.set(*usage_refresh + 1))
+    };
+
+    let on_glossaries_changed = {
+        let glossaries_version = glossaries_version.clone();
+        Callback::from(move |_| glossaries_version.set(*glossaries_version + 1))
+    };
+
+    let tab = |target: Page, label: &'static str| {
+        let page = page.clone();
+        html! {
+            <button
+                class={classes!("btn", "btn-small", if *page == target { "btn-primary" } else { "btn-secondary" })}
+                onclick={move |_| page.set(target)}
+            >
+                {label}
+            </button>
+        }
+    };
 
-    // PL -> EN translation states
-    let pl_to_en_text = use_state(|| String::new());
-    let pl_to_en_result = use_state(|| String::new());
-    let pl_to_en_loading = use_state(|| false);
+    if *checking_auth {
+        return html! {};
+    }
+
+    match &*auth {
+        Some(info) => html! {
+            <>
+                <div class="user-bar">
+                    <UsageBar refresh={*usage_refresh} />
+                    <nav class="tabs">
+                        {tab(Page::Translate, "Translate")}
+                        {tab(Page::Subtitles, "Subtitles")}
+                        {tab(Page::Glossaries, "Glossaries")}
+                    </nav>
+                    {if info.auth_required {
+                        html! {
+                            <>
+                                <span>{format!("Signed in as {}", info.user)}</span>
+                                <button class="btn btn-secondary btn-small" onclick={on_logout}>
+                                    {"Sign out"}
+                                </button>
+                            </>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                </div>
+                // Hidden rather than unmounted so typed text survives a visit to the glossaries
+                <div hidden={*page != Page::Translate}>
+                    <Translator
+                        on_request_done={on_request_done.clone()}
+                        glossaries_version={*glossaries_version}
+                    />
+                </div>
+                {if *page == Page::Subtitles {
+                    html! { <SubtitleTranslator on_request_done={on_request_done} /> }
+                } else {
+                    html! {}
+                }}
+                {if *page == Page::Glossaries {
+                    html! { <GlossaryManager admin={info.admin} on_change={on_glossaries_changed} /> }
+                } else {
+                    html! {}
+                }}
+            </>
+        },
+        None => html! { <Login on_login={on_login} /> },
+    }
+}
+
+/// "prefer_business" -> "Business (if supported)"
+fn option_label(value: &str) -> String {
+    let (name, preferred) = match value.strip_prefix("prefer_") {
+        Some(name) => (name, true),
+        None => (value, false),
+    };
+    let mut chars = name.chars();
+    let capitalized = chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default();
+    if preferred {
+        format!("{} (if supported)", capitalized)
+    } else {
+        capitalized
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct TranslatorProps {
+    /// Called after each request that reached the backend
+    on_request_done: Callback<()>,
+    /// Changing this value makes the glossary list reload
+    glossaries_version: u32,
+}
+
+#[function_component(Translator)]
+fn translator(props: &TranslatorProps) -> Html {
+    let languages = use_languages();
+    let glossaries = use_state(|| Rc::new(Vec::<GlossaryInfo>::new()));
+    // Panel ids keep component state attached to the right panel when one is removed
+    let panels = use_state(|| load_panels().into_iter().enumerate().collect::<Vec<_>>());
 
     // Improve text states
-    let improve_text = use_state(|| String::new());
-    let improve_result = use_state(|| String::new());
+    let improve_text = use_state(String::new);
+    let improve_result = use_state(|| None::<Result<ApiResponse, ApiError>>);
+    // Keys the diff view so accept/reject choices reset for every new result
+    let improve_version = use_state(|| 0u32);
     let improve_loading = use_state(|| false);
+    let writing_style = use_state(String::new);
+    let tone = use_state(String::new);
 
-    // EN -> PL input handler
-    let on_en_to_pl_input = {
-        let en_to_pl_text = en_to_pl_text.clone();
-        Callback::from(move |e: InputEvent| {
-            if let Some(input) = e.target_dyn_into::<web_sys::HtmlTextAreaElement>() {
-                en_to_pl_text.set(input.value());
-            }
+    // Not every provider has glossaries; the panels then simply offer none
+    {
+        let glossaries = glossaries.clone();
+        use_effect_with(props.glossaries_version, move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Ok(list) = get_json::<GlossaryList>("/glossaries").await {
+                    glossaries.set(Rc::new(list.glossaries));
+                }
+            });
+            || ()
+        });
+    }
+
+    let update_panels = {
+        let panels = panels.clone();
+        Callback::from(move |updated: Vec<(usize, PanelConfig)>| {
+            let configs: Vec<PanelConfig> = updated.iter().map(|(_, c)| c.clone()).collect();
+            save_panels(&configs);
+            panels.set(updated);
         })
     };
 
-    // PL -> EN input handler
-    let on_pl_to_en_input = {
-        let pl_to_en_text = pl_to_en_text.clone();
-        Callback::from(move |e: InputEvent| {
-            if let Some(input) = e.target_dyn_into::<web_sys::HtmlTextAreaElement>() {
-                pl_to_en_text.set(input.value());
-            }
+    let on_add_panel = {
+        let panels = panels.clone();
+        let update_panels = update_panels.clone();
+        Callback::from(move |_| {
+            let mut updated = (*panels).clone();
+            let next_id = updated.iter().map(|(id, _)| id + 1).max().unwrap_or(0);
+            updated.push((next_id, PanelConfig::new(None, "DE")));
+            update_panels.emit(updated);
         })
     };
 
@@ -64,121 +239,42 @@ fn app() -> Html {
         })
     };
 
-    // EN -> PL translate click handler
-    let on_en_to_pl_click = {
-        let en_to_pl_text = en_to_pl_text.clone();
-        let en_to_pl_result = en_to_pl_result.clone();
-        let en_to_pl_loading = en_to_pl_loading.clone();
-        Callback::from(move |_| {
-            let text = (*en_to_pl_text).clone();
-            let result = en_to_pl_result.clone();
-            let loading = en_to_pl_loading.clone();
-            
-            if text.trim().is_empty() {
-                return;
+    // DeepL Write accepts a style or a tone, never both
+    let on_style_change = {
+        let writing_style = writing_style.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                writing_style.set(select.value());
             }
-
-            loading.set(true);
-            wasm_bindgen_futures::spawn_local(async move {
-                let request = TranslateRequest {
-                    text,
-                    target_lang: Some("PL".to_string()),
-                };
-
-                match Request::post(&format!("{}/translate", get_backend_url()))
-                    .json(&request)
-                    .unwrap()
-                    .send()
-                    .await
-                {
-                    Ok(response) => {
-                        match response.json::<ApiResponse>().await {
-                            Ok(api_response) => {
-                                if api_response.success {
-                                    result.set(api_response.result);
-                                } else {
-                                    result.set(format!(
-                                        "Error: {}",
-                                        api_response.error.unwrap_or_else(|| "Unknown error".to_string())
-                                    ));
-                                }
-                            }
-                            Err(e) => {
-                                result.set(format!("Parse error: {}", e));
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        result.set(format!("Request error: {}", e));
-                    }
-                }
-                loading.set(false);
-            });
         })
     };
 
-    // PL -> EN translate click handler
-    let on_pl_to_en_click = {
-        let pl_to_en_text = pl_to_en_text.clone();
-        let pl_to_en_result = pl_to_en_result.clone();
-        let pl_to_en_loading = pl_to_en_loading.clone();
-        Callback::from(move |_| {
-            let text = (*pl_to_en_text).clone();
-            let result = pl_to_en_result.clone();
-            let loading = pl_to_en_loading.clone();
-            
-            if text.trim().is_empty() {
-                return;
+    let on_tone_change = {
+        let tone = tone.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                tone.set(select.value());
             }
-
-            loading.set(true);
-            wasm_bindgen_futures::spawn_local(async move {
-                let request = TranslateRequest {
-                    text,
-                    target_lang: Some("EN".to_string()),
-                };
-
-                match Request::post(&format!("{}/translate", get_backend_url()))
-                    .json(&request)
-                    .unwrap()
-                    .send()
-                    .await
-                {
-                    Ok(response) => {
-                        match response.json::<ApiResponse>().await {
-                            Ok(api_response) => {
-                                if api_response.success {
-                                    result.set(api_response.result);
-                                } else {
-                                    result.set(format!(
-                                        "Error: {}",
-                                        api_response.error.unwrap_or_else(|| "Unknown error".to_string())
-                                    ));
-                                }
-                            }
-                            Err(e) => {
-                                result.set(format!("Parse error: {}", e));
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        result.set(format!("Request error: {}", e));
-                    }
-                }
-                loading.set(false);
-            });
         })
     };
 
     // Improve click handler
     let on_improve_click = {
         let improve_text = improve_text.clone();
+        let writing_style = writing_style.clone();
+        let tone = tone.clone();
         let improve_result = improve_result.clone();
+        let improve_version = improve_version.clone();
         let improve_loading = improve_loading.clone();
+        let on_request_done = props.on_request_done.clone();
         Callback::from(move |_| {
+            let on_request_done = on_request_done.clone();
             let text = (*improve_text).clone();
             let result = improve_result.clone();
+            let version = improve_version.clone();
             let loading = improve_loading.clone();
+            let writing_style = Some((*writing_style).clone()).filter(|s| !s.is_empty());
+            let tone = Some((*tone).clone()).filter(|t| !t.is_empty());
             
             if text.trim().is_empty() {
                 return;
@@ -189,46 +285,47 @@ fn app() -> Html {
                 let request = ImproveRequest { 
                     text,
                     target_lang: None,
-                    writing_style: None,
-                    tone: None,
+                    writing_style,
+                    tone,
                 };
 
-                match Request::post(&format!("{}/improve", get_backend_url()))
-                    .json(&request)
-                    .unwrap()
-                    .send()
-                    .await
-                {
-                    Ok(response) => {
-                        match response.json::<ApiResponse>().await {
-                            Ok(api_response) => {
-                                if api_response.success {
-                                    result.set(api_response.result);
-                                } else {
-                                    result.set(format!(
-                                        "Error: {}",
-                                        api_response.error.unwrap_or_else(|| "Unknown error".to_string())
-                                    ));
-                                }
-                            }
-                            Err(e) => {
-                                result.set(format!("Parse error: {}", e));
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        result.set(format!("Request error: {}", e));
+                match post_api("/improve", &request).await {
+                    Ok(api_response) => {
+                        version.set(*version + 1);
+                        result.set(Some(Ok(api_response)));
                     }
+                    Err(error) => result.set(Some(Err(error))),
                 }
                 loading.set(false);
+                on_request_done.emit(());
             });
         })
     };
 
+    let can_remove = panels.len() > 1;
+
     html! {
         <div class="container">
         <div class="section">
                 <h2 class="section-title">{"Improve Text"}</h2>
+                <div class="panel-header">
+                    <select class="select" onchange={on_style_change} disabled={!tone.is_empty()}>
+                        <option value="" selected={writing_style.is_empty()}>{"Default style"}</option>
+                        {for WRITING_STYLES.iter().map(|style| html! {
+                            <option value={*style} selected={*writing_style == *style}>
+                                {option_label(style)}
+                            </option>
+                        })}
+                    </select>
+                    <select class="select" onchange={on_tone_change} disabled={!writing_style.is_empty()}>
+                        <option value="" selected={tone.is_empty()}>{"Default tone"}</option>
+                        {for TONES.iter().map(|t| html! {
+                            <option value={*t} selected={*tone == *t}>
+                                {option_label(t)}
+                            </option>
+                        })}
+                    </select>
+                </div>
                 <textarea
                     class="textarea"
                     rows="6"
@@ -246,83 +343,58 @@ fn app() -> Html {
                     {if *improve_loading { "Improving..." } else { "Improve Text" }}
                 </button>
                 
-                {if !improve_result.is_empty() {
-                    let is_error = improve_result.starts_with("Error:") || improve_result.starts_with("Parse error:") || improve_result.starts_with("Request error:");
-                    html! {
-                        <div class={classes!("result", is_error.then_some("error"))}>
+                {match &*improve_result {
+                    Some(Ok(ApiResponse { diff: Some(chunks), .. })) => html! {
+                        <DiffView key={*improve_version} chunks={chunks.clone()} />
+                    },
+                    Some(Ok(api_response)) => html! {
+                        <div class="result">
                             <h4 class="result-title">{"Improved Text:"}</h4>
-                            <p class="result-text">{(*improve_result).clone()}</p>
+                            <p class="result-text">{api_response.result.clone()}</p>
                         </div>
-                    }
-                } else {
-                    html! {}
+                    },
+                    Some(Err(error)) => render_error(error),
+                    None => html! {},
                 }}
             </div>
             
-            <div class="section">
-                <h2 class="section-title">{"Translate EN → PL"}</h2>
-                <textarea
-                    class="textarea"
-                    rows="6"
-                    cols="100"
-                    style="width: 90%; min-width: 00px; font-size: 16px; padding: 15px;"
-                    placeholder="Enter English text to translate to Polish..."
-                    value={(*en_to_pl_text).clone()}
-                    oninput={on_en_to_pl_input}
-                />
-                <button
-                    class={classes!("btn", "btn-primary", en_to_pl_loading.then_some("loading"))}
-                    onclick={on_en_to_pl_click}
-                    disabled={*en_to_pl_loading}
-                >
-                    {if *en_to_pl_loading { "Translating..." } else { "Translate to Polish" }}
-                </button>
-                
-                {if !en_to_pl_result.is_empty() {
-                    let is_error = en_to_pl_result.starts_with("Error:") || en_to_pl_result.starts_with("Parse error:") || en_to_pl_result.starts_with("Request error:");
-                    html! {
-                        <div class={classes!("result", is_error.then_some("error"))}>
-                            <h4 class="result-title">{"Polish Translation:"}</h4>
-                            <p class="result-text">{(*en_to_pl_result).clone()}</p>
-                        </div>
-                    }
-                } else {
-                    html! {}
-                }}
-            </div>
-
-            <div class="section">
-                <h2 class="section-title">{"Translate PL → EN"}</h2>
-                <textarea
-                    class="textarea"
-                    rows="6"
-                    cols="100"
-                    style="width: 90%; min-width: 400px; font-size: 16px; padding: 15px;"
-                    placeholder="Enter Polish text to translate to English..."
-                    value={(*pl_to_en_text).clone()}
-                    oninput={on_pl_to_en_input}
-                />
-                <button
-                    class={classes!("btn", "btn-secondary", pl_to_en_loading.then_some("loading"))}
-                    onclick={on_pl_to_en_click}
-                    disabled={*pl_to_en_loading}
-                >
-                    {if *pl_to_en_loading { "Translating..." } else { "Translate to English" }}
-                </button>
-                
-                {if !pl_to_en_result.is_empty() {
-                    let is_error = pl_to_en_result.starts_with("Error:") || pl_to_en_result.starts_with("Parse error:") || pl_to_en_result.starts_with("Request error:");
-                    html! {
-                        <div class={classes!("result", is_error.then_some("error"))}>
-                            <h4 class="result-title">{"English Translation:"}</h4>
-                            <p class="result-text">{(*pl_to_en_result).clone()}</p>
-                        </div>
-                    }
-                } else {
-                    html! {}
-                }}
-            </div>
+            {for panels.iter().map(|(id, config)| {
+                let id = *id;
+                let on_config_change = {
+                    let panels = panels.clone();
+                    let update_panels = update_panels.clone();
+                    Callback::from(move |config: PanelConfig| {
+                        let updated = panels
+                            .iter()
+                            .map(|(i, c)| (*i, if *i == id { config.clone() } else { c.clone() }))
+                            .collect();
+                        update_panels.emit(updated);
+                    })
+                };
+                let on_remove = can_remove.then(|| {
+                    let panels = panels.clone();
+                    let update_panels = update_panels.clone();
+                    Callback::from(move |_| {
+                        let updated = panels.iter().filter(|(i, _)| *i != id).cloned().collect();
+                        update_panels.emit(updated);
+                    })
+                });
+                html! {
+                    <TranslationPanel
+                        key={id}
+                        languages={languages.clone()}
+                        glossaries={(*glossaries).clone()}
+                        config={config.clone()}
+                        on_config_change={on_config_change}
+                        on_remove={on_remove}
+                        on_request_done={props.on_request_done.clone()}
+                    />
+                }
+            })}
 
+            <button class="btn btn-secondary" onclick={on_add_panel}>
+                {"+ Add language pair"}
+            </button>
         </div>
     }
-} 
\ No newline at end of file
+}
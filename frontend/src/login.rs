@@ -0,0 +1,85 @@
+use crate::api::{clear_token, get_json, store_token};
+use crate::render_error;
+use common::{ApiError, AuthInfo};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct LoginProps {
+    pub on_login: Callback<AuthInfo>,
+}
+
+#[function_component(Login)]
+pub fn login(props: &LoginProps) -> Html {
+    let token = use_state(String::new);
+    let error = use_state(|| None::<ApiError>);
+    let loading = use_state(|| false);
+
+    let on_token_input = {
+        let token = token.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(input) = e.target_dyn_into::<web_sys::HtmlInputElement>() {
+                token.set(input.value());
+            }
+        })
+    };
+
+    let on_submit = {
+        let token = token.clone();
+        let error = error.clone();
+        let loading = loading.clone();
+        let on_login = props.on_login.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let token = token.trim().to_string();
+            if token.is_empty() {
+                return;
+            }
+
+            let error = error.clone();
+            let loading = loading.clone();
+            let on_login = on_login.clone();
+            loading.set(true);
+            store_token(&token);
+            wasm_bindgen_futures::spawn_local(async move {
+                match get_json::<AuthInfo>("/auth/me").await {
+                    Ok(info) => on_login.emit(info),
+                    Err(e) => {
+                        clear_token();
+                        error.set(Some(e));
+                    }
+                }
+                loading.set(false);
+            });
+        })
+    };
+
+    html! {
+        <div class="container">
+            <div class="section login">
+                <h2 class="section-title">{"Sign in"}</h2>
+                <p>{"Enter the API token issued to you for this proxy."}</p>
+                <form onsubmit={on_submit}>
+                    <input
+                        class="input"
+                        type="password"
+                        placeholder="API token"
+                        value={(*token).clone()}
+                        oninput={on_token_input}
+                    />
+                    <button
+                        type="submit"
+                        class={classes!("btn", "btn-primary", loading.then_some("loading"))}
+                        disabled={*loading}
+                    >
+                        {if *loading { "Signing in..." } else { "Sign in" }}
+                    </button>
+                </form>
+                {if let Some(error) = &*error {
+                    render_error(error)
+                } else {
+                    html! {}
+                }}
+            </div>
+        </div>
+    }
+}
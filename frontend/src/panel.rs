@@ -0,0 +1,467 @@
+use crate::api::{get_json, post_api};
+use crate::render_error;
+use common::{ApiError, AppliedTerm, GlossaryInfo, Language, LanguagesResponse, TranslateRequest};
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use yew::prelude::*;
+
+const PANELS_STORAGE_KEY: &str = "deepl-proxy.panels";
+
+/// Language pair of one translation panel. `source: None` means auto-detect.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PanelConfig {
+    pub source: Option<String>,
+    pub target: String,
+    /// "more" or "less"; only offered for targets that support formality
+    #[serde(default)]
+    pub formality: Option<String>,
+    /// Only used while a glossary for this exact pair still exists
+    #[serde(default)]
+    pub glossary_id: Option<String>,
+}
+
+impl PanelConfig {
+    pub fn new(source: Option<&str>, target: &str) -> Self {
+        Self {
+            source: source.map(str::to_string),
+            target: target.to_string(),
+            formality: None,
+            glossary_id: None,
+        }
+    }
+}
+
+/// Panels remembered from the last visit, or EN -> PL and PL -> EN
+pub fn load_panels() -> Vec<PanelConfig> {
+    LocalStorage::get(PANELS_STORAGE_KEY)
+        .ok()
+        .filter(|panels: &Vec<PanelConfig>| !panels.is_empty())
+        .unwrap_or_else(|| {
+            vec![
+                PanelConfig::new(Some("EN"), "PL"),
+                PanelConfig::new(Some("PL"), "EN-US"),
+            ]
+        })
+}
+
+pub fn save_panels(panels: &[PanelConfig]) {
+    let _ = LocalStorage::set(PANELS_STORAGE_KEY, panels);
+}
+
+/// Used until the backend's list has loaded, or if it cannot be fetched
+fn fallback_languages() -> LanguagesResponse {
+    let language = |code: &str, name: &str| Language {
+        code: code.to_string(),
+        name: name.to_string(),
+        supports_formality: matches!(code, "PL" | "DE" | "FR"),
+    };
+    LanguagesResponse {
+        source: vec![
+            language("EN", "English"),
+            language("PL", "Polish"),
+            language("DE", "German"),
+            language("UK", "Ukrainian"),
+            language("FR", "French"),
+        ],
+        target: vec![
+            language("EN-GB", "English (British)"),
+            language("EN-US", "English (American)"),
+            language("PL", "Polish"),
+            language("DE", "German"),
+            language("UK", "Ukrainian"),
+            language("FR", "French"),
+        ],
+    }
+}
+
+/// The backend's language list, or the fallback until (or unless) it loads
+#[hook]
+pub fn use_languages() -> Rc<LanguagesResponse> {
+    let languages = use_state(|| Rc::new(fallback_languages()));
+    {
+        let languages = languages.clone();
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Ok(list) = get_json::<LanguagesResponse>("/languages").await {
+                    languages.set(Rc::new(list));
+                }
+            });
+            || ()
+        });
+    }
+    (*languages).clone()
+}
+
+// Target codes may carry a region ("EN-GB") that source codes never have
+fn target_to_source(languages: &LanguagesResponse, target: &str) -> Option<String> {
+    let base = target.split('-').next().unwrap_or(target);
+    languages
+        .source
+        .iter()
+        .find(|l| l.code.eq_ignore_ascii_case(base))
+        .map(|l| l.code.clone())
+}
+
+fn source_to_target(languages: &LanguagesResponse, source: &str) -> Option<String> {
+    let exact = languages
+        .target
+        .iter()
+        .find(|l| l.code.eq_ignore_ascii_case(source));
+    let regional = || {
+        languages.target.iter().find(|l| {
+            l.code
+                .split_once('-')
+                .is_some_and(|(base, _)| base.eq_ignore_ascii_case(source))
+        })
+    };
+    exact.or_else(regional).map(|l| l.code.clone())
+}
+
+// Mirrors the backend's lookup, where "EN" also matches "EN-GB"
+fn supports_formality(languages: &LanguagesResponse, target: &str) -> bool {
+    languages
+        .target
+        .iter()
+        .find(|l| l.code.eq_ignore_ascii_case(target))
+        .or_else(|| {
+            languages.target.iter().find(|l| {
+                l.code
+                    .split_once('-')
+                    .is_some_and(|(base, _)| base.eq_ignore_ascii_case(target))
+            })
+        })
+        .is_some_and(|l| l.supports_formality)
+}
+
+// Glossaries are defined on base languages, so "EN-GB" uses an EN glossary
+fn matching_glossaries<'a>(
+    glossaries: &'a [GlossaryInfo],
+    config: &PanelConfig,
+) -> Vec<&'a GlossaryInfo> {
+    let Some(source) = config.source.as_deref() else {
+        return Vec::new();
+    };
+    let target_base = config.target.split('-').next().unwrap_or(&config.target);
+    glossaries
+        .iter()
+        .filter(|g| {
+            g.ready
+                && g.source_lang.eq_ignore_ascii_case(source)
+                && g.target_lang.eq_ignore_ascii_case(target_base)
+        })
+        .collect()
+}
+
+pub(crate) fn language_name(languages: &[Language], code: &str) -> String {
+    languages
+        .iter()
+        .find(|l| l.code.eq_ignore_ascii_case(code))
+        .map(|l| l.name.clone())
+        .unwrap_or_else(|| code.to_string())
+}
+
+#[derive(Properties, PartialEq)]
+pub struct TranslationPanelProps {
+    pub languages: Rc<LanguagesResponse>,
+    pub glossaries: Rc<Vec<GlossaryInfo>>,
+    pub config: PanelConfig,
+    pub on_config_change: Callback<PanelConfig>,
+    /// Absent for the last remaining panel
+    pub on_remove: Option<Callback<()>>,
+    /// Called after each request that reached the backend
+    pub on_request_done: Callback<()>,
+}
+
+/// Textarea with source/target pickers, a swap button and the translation
+#[function_component(TranslationPanel)]
+pub fn translation_panel(props: &TranslationPanelProps) -> Html {
+    let text = use_state(String::new);
+    let result = use_state(|| None::<Result<String, ApiError>>);
+    let detected = use_state(|| None::<String>);
+    let applied_terms = use_state(Vec::<AppliedTerm>::new);
+    let loading = use_state(|| false);
+
+    let on_input = {
+        let text = text.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(input) = e.target_dyn_into::<web_sys::HtmlTextAreaElement>() {
+                text.set(input.value());
+            }
+        })
+    };
+
+    let on_source_change = {
+        let config = props.config.clone();
+        let on_config_change = props.on_config_change.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                let value = select.value();
+                on_config_change.emit(PanelConfig {
+                    source: (!value.is_empty()).then_some(value),
+                    glossary_id: None,
+                    ..config.clone()
+                });
+            }
+        })
+    };
+
+    let on_target_change = {
+        let config = props.config.clone();
+        let languages = props.languages.clone();
+        let on_config_change = props.on_config_change.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                let target = select.value();
+                let formality = config
+                    .formality
+                    .clone()
+                    .filter(|_| supports_formality(&languages, &target));
+                on_config_change.emit(PanelConfig {
+                    target,
+                    formality,
+                    glossary_id: None,
+                    ..config.clone()
+                });
+            }
+        })
+    };
+
+    let on_formality_change = {
+        let config = props.config.clone();
+        let on_config_change = props.on_config_change.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                let value = select.value();
+                on_config_change.emit(PanelConfig {
+                    formality: (!value.is_empty()).then_some(value),
+                    ..config.clone()
+                });
+            }
+        })
+    };
+
+    let on_glossary_change = {
+        let config = props.config.clone();
+        let on_config_change = props.on_config_change.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                let value = select.value();
+                on_config_change.emit(PanelConfig {
+                    glossary_id: (!value.is_empty()).then_some(value),
+                    ..config.clone()
+                });
+            }
+        })
+    };
+
+    // With auto-detect the detected language becomes the new target
+    let swap_source = props.config.source.clone().or_else(|| (*detected).clone());
+    let on_swap = {
+        let config = props.config.clone();
+        let languages = props.languages.clone();
+        let on_config_change = props.on_config_change.clone();
+        let text = text.clone();
+        let result = result.clone();
+        let detected = detected.clone();
+        let swap_source = swap_source.clone();
+        Callback::from(move |_| {
+            let Some(new_target) = swap_source
+                .as_deref()
+                .and_then(|source| source_to_target(&languages, source))
+            else {
+                return;
+            };
+            let new_source = target_to_source(&languages, &config.target);
+
+            // Keep translating in the other direction from the last result
+            if let Some(Ok(translated)) = &*result {
+                text.set(translated.clone());
+                result.set(None);
+                detected.set(None);
+            }
+            let formality = config
+                .formality
+                .clone()
+                .filter(|_| supports_formality(&languages, &new_target));
+            on_config_change.emit(PanelConfig {
+                source: new_source,
+                target: new_target,
+                formality,
+                glossary_id: None,
+            });
+        })
+    };
+
+    let glossaries = matching_glossaries(&props.glossaries, &props.config);
+    let glossary_id = props
+        .config
+        .glossary_id
+        .clone()
+        .filter(|id| glossaries.iter().any(|g| &g.glossary_id == id));
+
+    let on_translate_click = {
+        let config = props.config.clone();
+        let glossary_id = glossary_id.clone();
+        let text = text.clone();
+        let result = result.clone();
+        let detected = detected.clone();
+        let applied_terms = applied_terms.clone();
+        let loading = loading.clone();
+        let on_request_done = props.on_request_done.clone();
+        Callback::from(move |_| {
+            let text = (*text).clone();
+            if text.trim().is_empty() {
+                return;
+            }
+
+            let config = config.clone();
+            let glossary_id = glossary_id.clone();
+            let result = result.clone();
+            let detected = detected.clone();
+            let applied_terms = applied_terms.clone();
+            let loading = loading.clone();
+            let on_request_done = on_request_done.clone();
+            loading.set(true);
+            detected.set(None);
+            wasm_bindgen_futures::spawn_local(async move {
+                let request = TranslateRequest {
+                    text,
+                    source_lang: config.source,
+                    target_lang: Some(config.target),
+                    formality: config.formality,
+                    glossary_id,
+                    tags: Default::default(),
+                    protect_placeholders: false,
+                    format: Default::default(),
+                };
+
+                match post_api("/translate", &request).await {
+                    Ok(api_response) => {
+                        detected.set(api_response.detected_source_language);
+                        applied_terms.set(api_response.applied_terms);
+                        result.set(Some(Ok(api_response.result)));
+                    }
+                    Err(error) => result.set(Some(Err(error))),
+                }
+                loading.set(false);
+                on_request_done.emit(());
+            });
+        })
+    };
+
+    let source_value = props.config.source.clone().unwrap_or_default();
+    let target_name = language_name(&props.languages.target, &props.config.target);
+    let formality_supported = supports_formality(&props.languages, &props.config.target);
+    let formality_value = props.config.formality.clone().unwrap_or_default();
+
+    html! {
+        <div class="section">
+            <div class="panel-header">
+                <select class="select" onchange={on_source_change}>
+                    <option value="" selected={source_value.is_empty()}>{"Detect language"}</option>
+                    {for props.languages.source.iter().map(|l| html! {
+                        <option value={l.code.clone()} selected={l.code.eq_ignore_ascii_case(&source_value)}>
+                            {&l.name}
+                        </option>
+                    })}
+                </select>
+                <button
+                    class="btn btn-secondary btn-small"
+                    title="Swap languages"
+                    onclick={on_swap}
+                    disabled={swap_source.is_none()}
+                >
+                    {"⇄"}
+                </button>
+                <select class="select" onchange={on_target_change}>
+                    {for props.languages.target.iter().map(|l| html! {
+                        <option value={l.code.clone()} selected={l.code.eq_ignore_ascii_case(&props.config.target)}>
+                            {&l.name}
+                        </option>
+                    })}
+                </select>
+                <select
+                    class="select"
+                    title={if formality_supported { "Formality" } else { "This language has no formal/informal distinction" }}
+                    onchange={on_formality_change}
+                    disabled={!formality_supported}
+                >
+                    <option value="" selected={formality_value.is_empty()}>{"Default formality"}</option>
+                    <option value="more" selected={formality_value == "more"}>{"Formal"}</option>
+                    <option value="less" selected={formality_value == "less"}>{"Informal"}</option>
+                </select>
+                {if glossaries.is_empty() {
+                    html! {}
+                } else {
+                    html! {
+                        <select class="select" title="Glossary" onchange={on_glossary_change}>
+                            <option value="" selected={glossary_id.is_none()}>{"No glossary"}</option>
+                            {for glossaries.iter().map(|g| html! {
+                                <option
+                                    value={g.glossary_id.clone()}
+                                    selected={glossary_id.as_deref() == Some(g.glossary_id.as_str())}
+                                >
+                                    {&g.name}
+                                </option>
+                            })}
+                        </select>
+                    }
+                }}
+                {if let Some(on_remove) = props.on_remove.clone() {
+                    html! {
+                        <button
+                            class="btn btn-secondary btn-small panel-remove"
+                            title="Remove panel"
+                            onclick={move |_| on_remove.emit(())}
+                        >
+                            {"✕"}
+                        </button>
+                    }
+                } else {
+                    html! {}
+                }}
+            </div>
+            <textarea
+                class="textarea"
+                rows="6"
+                cols="100"
+                placeholder={format!("Enter text to translate to {}...", target_name)}
+                value={(*text).clone()}
+                oninput={on_input}
+            />
+            <button
+                class={classes!("btn", "btn-primary", loading.then_some("loading"))}
+                onclick={on_translate_click}
+                disabled={*loading}
+            >
+                {if *loading { "Translating...".to_string() } else { format!("Translate to {}", target_name) }}
+            </button>
+
+            {match &*result {
+                Some(Ok(translated)) => html! {
+                    <div class="result">
+                        <h4 class="result-title">{format!("{} Translation:", target_name)}</h4>
+                        <p class="result-text">{translated.clone()}</p>
+                        {if let Some(lang) = &*detected {
+                            html! { <p class="result-meta">{format!("Detected language: {}", lang)}</p> }
+                        } else {
+                            html! {}
+                        }}
+                        {if applied_terms.is_empty() {
+                            html! {}
+                        } else {
+                            let terms: Vec<String> = applied_terms
+                                .iter()
+                                .map(|t| format!("{} → {}", t.source, t.target))
+                                .collect();
+                            html! { <p class="result-meta">{format!("Terminology: {}", terms.join(", "))}</p> }
+                        }}
+                    </div>
+                },
+                Some(Err(error)) => render_error(error),
+                None => html! {},
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,226 @@
+use crate::api::post_json;
+use crate::panel::{language_name, use_languages};
+use crate::render_error;
+use common::{ApiError, SubtitleFormat, SubtitleTranslateRequest, SubtitleTranslateResponse};
+use gloo_file::{Blob, ObjectUrl};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct SubtitleTranslatorProps {
+    /// Called after every request that may have used quota
+    pub on_request_done: Callback<()>,
+}
+
+// The uploaded file
+struct SubtitleUpload {
+    name: String,
+    content: String,
+}
+
+// A translated file ready to be downloaded
+struct SubtitleDownload {
+    response: SubtitleTranslateResponse,
+    file_name: String,
+    url: ObjectUrl,
+}
+
+/// Translates an uploaded SRT or WebVTT file and offers the result as a download
+#[function_component(SubtitleTranslator)]
+pub fn subtitle_translator(props: &SubtitleTranslatorProps) -> Html {
+    let languages = use_languages();
+    let upload = use_state(|| None::<SubtitleUpload>);
+    let source = use_state(String::new);
+    let target = use_state(|| "PL".to_string());
+    let loading = use_state(|| false);
+    let result = use_state(|| None::<Result<SubtitleDownload, ApiError>>);
+
+    let on_file_change = {
+        let upload = upload.clone();
+        let result = result.clone();
+        Callback::from(move |e: Event| {
+            let Some(input) = e.target_dyn_into::<web_sys::HtmlInputElement>() else {
+                return;
+            };
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            let file = gloo_file::File::from(file);
+            let upload = upload.clone();
+            let result = result.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match gloo_file::futures::read_as_text(&file).await {
+                    Ok(content) => {
+                        upload.set(Some(SubtitleUpload {
+                            name: file.name(),
+                            content,
+                        }));
+                        result.set(None);
+                    }
+                    Err(e) => result.set(Some(Err(ApiError::BadInput(format!(
+                        "Could not read {}: {}",
+                        file.name(),
+                        e
+                    ))))),
+                }
+            });
+        })
+    };
+
+    let on_source_change = {
+        let source = source.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                source.set(select.value());
+            }
+        })
+    };
+
+    let on_target_change = {
+        let target = target.clone();
+        Callback::from(move |e: Event| {
+            if let Some(select) = e.target_dyn_into::<web_sys::HtmlSelectElement>() {
+                target.set(select.value());
+            }
+        })
+    };
+
+    let on_translate = {
+        let upload = upload.clone();
+        let source = source.clone();
+        let target = target.clone();
+        let loading = loading.clone();
+        let result = result.clone();
+        let on_request_done = props.on_request_done.clone();
+        Callback::from(move |_| {
+            let Some(file) = upload.as_ref() else {
+                return;
+            };
+            let lower_name = file.name.to_lowercase();
+            let format = if lower_name.ends_with(".vtt") {
+                Some(SubtitleFormat::Vtt)
+            } else if lower_name.ends_with(".srt") {
+                Some(SubtitleFormat::Srt)
+            } else {
+                None
+            };
+            let request = SubtitleTranslateRequest {
+                content: file.content.clone(),
+                format,
+                source_lang: (!source.is_empty()).then(|| (*source).clone()),
+                target_lang: Some((*target).clone()),
+                formality: None,
+                glossary_id: None,
+            };
+            // "talk.en.srt" -> "talk.en.pl.srt"
+            let stem = file
+                .name
+                .rsplit_once('.')
+                .map_or(file.name.as_str(), |(stem, _)| stem)
+                .to_string();
+            let target_code = target.to_lowercase();
+
+            let loading = loading.clone();
+            let result = result.clone();
+            let on_request_done = on_request_done.clone();
+            loading.set(true);
+            wasm_bindgen_futures::spawn_local(async move {
+                let response =
+                    post_json::<_, SubtitleTranslateResponse>("/translate/subtitles", &request)
+                        .await
+                        .map(|response| {
+                            let blob = Blob::new_with_options(
+                                response.content.as_str(),
+                                Some(response.format.mime_type()),
+                            );
+                            SubtitleDownload {
+                                file_name: format!(
+                                    "{}.{}.{}",
+                                    stem,
+                                    target_code,
+                                    response.format.extension()
+                                ),
+                                url: ObjectUrl::from(blob),
+                                response,
+                            }
+                        });
+                result.set(Some(response));
+                loading.set(false);
+                on_request_done.emit(());
+            });
+        })
+    };
+
+    html! {
+        <div class="container">
+            <div class="section">
+                <h2 class="section-title">{"Subtitles"}</h2>
+                <div class="panel-header">
+                    <input type="file" accept=".srt,.vtt" onchange={on_file_change} />
+                    <select class="select" onchange={on_source_change}>
+                        <option value="" selected={source.is_empty()}>{"Detect language"}</option>
+                        {for languages.source.iter().map(|l| html! {
+                            <option value={l.code.clone()} selected={l.code == *source}>{&l.name}</option>
+                        })}
+                    </select>
+                    {"→"}
+                    <select class="select" onchange={on_target_change}>
+                        {for languages.target.iter().map(|l| html! {
+                            <option value={l.code.clone()} selected={l.code == *target}>{&l.name}</option>
+                        })}
+                    </select>
+                </div>
+                <button
+                    class={classes!("btn", "btn-primary", loading.then_some("loading"))}
+                    onclick={on_translate}
+                    disabled={*loading || upload.is_none()}
+                >
+                    {if *loading { "Translating..." } else { "Translate File" }}
+                </button>
+
+                {match &*result {
+                    Some(Ok(download)) => html! {
+                        <div class="result">
+                            <h4 class="result-title">{"Translated subtitles"}</h4>
+                            <p class="result-meta">
+                                {format!("{} cues", download.response.cues)}
+                            </p>
+                            {if let Some(lang) = &download.response.detected_source_language {
+                                html! {
+                                    <p class="result-meta">
+                                        {format!("Detected language: {}", language_name(&languages.source, lang))}
+                                    </p>
+                                }
+                            } else {
+                                html! {}
+                            }}
+                            {if download.response.untranslated_cues.is_empty() {
+                                html! {}
+                            } else {
+                                let cues: Vec<String> = download
+                                    .response
+                                    .untranslated_cues
+                                    .iter()
+                                    .map(usize::to_string)
+                                    .collect();
+                                html! {
+                                    <p class="result-meta">
+                                        {format!("Left untranslated because their tags were lost: cues {}", cues.join(", "))}
+                                    </p>
+                                }
+                            }}
+                            <a
+                                class="btn btn-secondary btn-small"
+                                href={download.url.to_string()}
+                                download={download.file_name.clone()}
+                            >
+                                {format!("Download {}", download.file_name)}
+                            </a>
+                        </div>
+                    },
+                    Some(Err(error)) => render_error(error),
+                    None => html! {},
+                }}
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,69 @@
+use crate::api::get_json;
+use common::{UsagePeriod, UsageReport};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct UsageBarProps {
+    /// Changing this value makes the bar fetch fresh numbers
+    pub refresh: u32,
+}
+
+fn render_period(label: &str, period: &UsagePeriod) -> Html {
+    match period.limit {
+        Some(limit) => {
+            let percent = if limit == 0 {
+                100.0
+            } else {
+                (period.used as f64 / limit as f64 * 100.0).min(100.0)
+            };
+            html! {
+                <div class="usage-period">
+                    <div class="usage-label">
+                        {format!("{}: {} / {} characters", label, period.used, limit)}
+                    </div>
+                    <div class="usage-track">
+                        <div
+                            class={classes!("usage-fill", (percent >= 90.0).then_some("usage-high"))}
+                            style={format!("width: {:.1}%", percent)}
+                        />
+                    </div>
+                </div>
+            }
+        }
+        None => html! {
+            <div class="usage-period">
+                <div class="usage-label">
+                    {format!("{}: {} characters (no limit)", label, period.used)}
+                </div>
+            </div>
+        },
+    }
+}
+
+/// Shows the caller's character usage against their daily and monthly quota
+#[function_component(UsageBar)]
+pub fn usage_bar(props: &UsageBarProps) -> Html {
+    let report = use_state(|| None::<UsageReport>);
+
+    {
+        let report = report.clone();
+        use_effect_with(props.refresh, move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Ok(usage) = get_json::<UsageReport>("/usage").await {
+                    report.set(Some(usage));
+                }
+            });
+            || ()
+        });
+    }
+
+    match &*report {
+        Some(report) => html! {
+            <div class="usage">
+                {render_period("Today", &report.daily)}
+                {render_period("This month", &report.monthly)}
+            </div>
+        },
+        None => html! {},
+    }
+}